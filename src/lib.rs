@@ -1,34 +1,88 @@
 #![no_std]
 
 extern crate alloc;
+extern crate std;
 
+mod scxml;
+
+use alloc::boxed::Box;
 use alloc::collections::BTreeSet;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt::Write;
 use proc_macro::TokenStream;
-use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens};
 use syn::{
+    ext::IdentExt,
     parse::{Parse, ParseStream},
     parse_macro_input,
     punctuated::Punctuated,
     token::Comma,
-    Error, Ident, Result, Token,
+    Error, GenericParam, Generics, Ident, LitBool, LitInt, LitStr, Result, Token, WherePredicate,
 };
 
+const RENAME_CASES: [&str; 6] = [
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "lowercase",
+    "UPPERCASE",
+];
+
+enum MachineInput {
+    Inline(Box<StateMachine>),
+    File(LitStr),
+}
+
+#[derive(Default)]
 struct StateMachine {
     name: Option<Ident>,
+    generics: Generics,
+    initial: Option<Ident>,
     derive_states: Option<Vec<Ident>>,
     derive_events: Option<Vec<Ident>>,
+    allow_unreachable: Vec<Ident>,
+    allow_similar: Vec<Ident>,
+    terminal: Vec<Ident>,
+    exhaustive: Option<LitBool>,
+    ignore: Vec<IgnoredEvents>,
+    codegen: Codegen,
+    repr: Option<Ident>,
+    rename_all: Option<Ident>,
+    states: Vec<DeclaredVariant>,
+    events: Vec<DeclaredVariant>,
     transitions: Vec<Transition>,
 }
 
+#[derive(Default)]
+enum Codegen {
+    #[default]
+    Match,
+    Table(Ident),
+}
+
+struct DeclaredVariant {
+    ident: Ident,
+    fields: syn::Fields,
+    discriminant: Option<LitInt>,
+}
+
+struct IgnoredEvents {
+    states: StatePattern,
+    events: Vec<Ident>,
+}
+
 struct Transition {
     states: StatePattern,
     events: Vec<Ident>,
     target: TargetState,
+    precedence: Option<Span>,
+    line: usize,
+    weight: u64,
 }
 
 enum StatePattern {
@@ -42,16 +96,49 @@ enum TargetState {
     Internal,
 }
 
+impl Parse for MachineInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(syn::Ident) && input.peek2(Token![=]) {
+            let ident: Ident = input.parse()?;
+            if ident != "file" {
+                return Err(Error::new(ident.span(), "Expected 'file'"));
+            }
+            input.parse::<Token![=]>()?;
+            let path = input.parse::<LitStr>()?;
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+            return Ok(MachineInput::File(path));
+        }
+
+        input
+            .parse()
+            .map(|state_machine| MachineInput::Inline(Box::new(state_machine)))
+    }
+}
+
 impl Parse for StateMachine {
     fn parse(input: ParseStream) -> Result<Self> {
         let mut name = None;
+        let mut generics = Generics::default();
+        let mut initial = None;
         let mut derive_states = None;
         let mut derive_events = None;
+        let mut allow_unreachable = Vec::new();
+        let mut allow_similar = Vec::new();
+        let mut terminal = Vec::new();
+        let mut exhaustive = None;
+        let mut ignore = Vec::new();
+        let mut codegen = Codegen::Match;
+        let mut repr = None;
+        let mut rename_all = None;
+        let mut states = Vec::new();
+        let mut events = Vec::new();
 
         while !input.peek(syn::Ident) || input.peek2(Token![:]) {
             let lookahead = input.lookahead1();
-            if lookahead.peek(syn::Ident) {
-                let ident: Ident = input.parse()?;
+            if lookahead.peek(syn::Ident) || input.peek(Token![where]) {
+                let ident = input.call(Ident::parse_any)?;
                 input.parse::<Token![:]>()?;
 
                 if ident == "name" {
@@ -59,6 +146,27 @@ impl Parse for StateMachine {
                     if input.peek(Token![,]) {
                         input.parse::<Token![,]>()?;
                     }
+                } else if ident == "generics" {
+                    let where_clause = generics.where_clause.take();
+                    generics = input.parse::<Generics>()?;
+                    generics.where_clause = where_clause;
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "where" {
+                    let content;
+                    syn::braced!(content in input);
+                    let predicates =
+                        Punctuated::<WherePredicate, Comma>::parse_terminated(&content)?;
+                    generics.make_where_clause().predicates.extend(predicates);
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "initial" {
+                    initial = Some(input.parse::<Ident>()?);
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
                 } else if ident == "derive_states" {
                     let content;
                     syn::bracketed!(content in input);
@@ -75,22 +183,124 @@ impl Parse for StateMachine {
                     if input.peek(Token![,]) {
                         input.parse::<Token![,]>()?;
                     }
+                } else if ident == "allow_similar" {
+                    let content;
+                    syn::bracketed!(content in input);
+                    let names = Punctuated::<Ident, Comma>::parse_terminated(&content)?;
+                    allow_similar = names.into_iter().collect();
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "allow_unreachable" {
+                    let content;
+                    syn::bracketed!(content in input);
+                    let states = Punctuated::<Ident, Comma>::parse_terminated(&content)?;
+                    allow_unreachable = states.into_iter().collect();
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "terminal" {
+                    let content;
+                    syn::bracketed!(content in input);
+                    let states = Punctuated::<Ident, Comma>::parse_terminated(&content)?;
+                    terminal = states.into_iter().collect();
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "exhaustive" {
+                    exhaustive = Some(input.parse::<LitBool>()?);
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "codegen" {
+                    let backend = input.call(Ident::parse_any)?;
+                    codegen = if backend == "match" {
+                        Codegen::Match
+                    } else if backend == "table" {
+                        Codegen::Table(backend)
+                    } else {
+                        return Err(Error::new(backend.span(), "Expected 'match' or 'table'"));
+                    };
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "repr" {
+                    let ty = input.parse::<Ident>()?;
+                    if ty != "u8" && ty != "u16" {
+                        return Err(Error::new(ty.span(), "Expected 'u8' or 'u16'"));
+                    }
+                    repr = Some(ty);
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "rename_all" {
+                    let case = input.parse::<Ident>()?;
+                    if !RENAME_CASES.iter().any(|name| case == name) {
+                        return Err(Error::new(
+                            case.span(),
+                            "Expected 'PascalCase', 'camelCase', 'snake_case', 'SCREAMING_SNAKE_CASE', 'lowercase', or 'UPPERCASE'",
+                        ));
+                    }
+                    rename_all = Some(case);
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "states" || ident == "events" {
+                    let content;
+                    syn::braced!(content in input);
+                    let variants =
+                        Punctuated::<DeclaredVariant, Comma>::parse_terminated(&content)?;
+                    if ident == "states" {
+                        states = variants.into_iter().collect();
+                    } else {
+                        events = variants.into_iter().collect();
+                    }
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
+                } else if ident == "ignore" {
+                    let content;
+                    syn::braced!(content in input);
+                    let ignored = Punctuated::<IgnoredEvents, Comma>::parse_terminated(&content)?;
+                    ignore = ignored.into_iter().collect();
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                    }
                 } else if ident == "transitions" {
                     let transitions_content;
                     syn::braced!(transitions_content in input);
                     let transition_list =
                         Punctuated::<Transition, Comma>::parse_terminated(&transitions_content)?;
-                    let transitions = transition_list.into_iter().collect();
+                    let transitions: Vec<Transition> = transition_list.into_iter().collect();
+                    if transitions.is_empty() {
+                        return Err(Error::new(
+                            ident.span(),
+                            "state machine has no transitions\n\
+                             help: add at least one transition, such as `*Idle + Start = Running`",
+                        ));
+                    }
                     return Ok(StateMachine {
                         name,
+                        generics,
+                        initial,
                         derive_states,
                         derive_events,
+                        allow_unreachable,
+                        allow_similar,
+                        terminal,
+                        exhaustive,
+                        ignore,
+                        codegen,
+                        repr,
+                        rename_all,
+                        states,
+                        events,
                         transitions,
                     });
                 } else {
                     return Err(Error::new(
                         ident.span(),
-                        "Expected 'name', 'derive_states', 'derive_events', or 'transitions'",
+                        "Expected 'name', 'generics', 'where', 'initial', 'derive_states', 'derive_events', 'allow_unreachable', 'allow_similar', 'terminal', 'exhaustive', 'ignore', 'codegen', 'repr', 'rename_all', 'states', 'events', or 'transitions'",
                     ));
                 }
             } else {
@@ -104,6 +314,33 @@ impl Parse for StateMachine {
 
 impl Parse for Transition {
     fn parse(input: ParseStream) -> Result<Self> {
+        let mut precedence = None;
+        let mut weight = 1;
+        for attribute in input.call(syn::Attribute::parse_outer)? {
+            if attribute.path.is_ident("precedence") && attribute.tokens.is_empty() {
+                precedence = Some(attribute.pound_token.span);
+            } else if attribute.path.is_ident("weight") {
+                match attribute.parse_meta() {
+                    Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                        lit: syn::Lit::Int(lit),
+                        ..
+                    })) => weight = lit.base10_parse::<u64>()?,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            attribute,
+                            "Expected '#[weight = N]' with an integer weight",
+                        ))
+                    }
+                }
+            } else {
+                return Err(Error::new_spanned(
+                    attribute,
+                    "Expected '#[precedence]', '#[weight = N]', or a transition",
+                ));
+            }
+        }
+
+        let line = input.span().start().line;
         let states = input.parse::<StatePattern>()?;
         input.parse::<Token![+]>()?;
 
@@ -131,10 +368,54 @@ impl Parse for Transition {
             states,
             events,
             target,
+            precedence,
+            line,
+            weight,
+        })
+    }
+}
+
+impl Parse for DeclaredVariant {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident = input.parse::<Ident>()?;
+        let fields = if input.peek(syn::token::Paren) {
+            syn::Fields::Unnamed(input.parse()?)
+        } else if input.peek(syn::token::Brace) {
+            syn::Fields::Named(input.parse()?)
+        } else {
+            syn::Fields::Unit
+        };
+        let discriminant = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse::<LitInt>()?)
+        } else {
+            None
+        };
+        Ok(DeclaredVariant {
+            ident,
+            fields,
+            discriminant,
         })
     }
 }
 
+impl Parse for IgnoredEvents {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let states = input.parse::<StatePattern>()?;
+        input.parse::<Token![+]>()?;
+
+        let mut events = Vec::new();
+        events.push(input.parse::<Ident>()?);
+
+        while input.peek(Token![|]) {
+            input.parse::<Token![|]>()?;
+            events.push(input.parse::<Ident>()?);
+        }
+
+        Ok(IgnoredEvents { states, events })
+    }
+}
+
 impl Parse for StatePattern {
     fn parse(input: ParseStream) -> Result<Self> {
         if input.peek(Token![_]) {
@@ -171,6 +452,17 @@ impl Parse for StatePattern {
     }
 }
 
+fn combine_errors(errors: impl IntoIterator<Item = Error>) -> Result<()> {
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut error) => {
+            error.extend(errors);
+            Err(error)
+        }
+        None => Ok(()),
+    }
+}
+
 fn validate_no_duplicate_transitions(transitions: &[Transition]) -> Result<()> {
     let mut seen = BTreeSet::new();
 
@@ -182,7 +474,7 @@ fn validate_no_duplicate_transitions(transitions: &[Transition]) -> Result<()> {
             StatePattern::Multiple { states } => {
                 states.iter().map(|(ident, _)| ident.to_string()).collect()
             }
-            StatePattern::Wildcard => continue,
+            StatePattern::Wildcard => alloc::vec!["_".to_string()],
         };
 
         for state_str in state_idents {
@@ -207,171 +499,2353 @@ fn validate_no_duplicate_transitions(transitions: &[Transition]) -> Result<()> {
     Ok(())
 }
 
-#[proc_macro]
-pub fn statemachine(input: TokenStream) -> TokenStream {
-    let state_machine = parse_macro_input!(input as StateMachine);
+fn describe_sources(pattern: &StatePattern) -> String {
+    match pattern {
+        StatePattern::Single { ident, .. } => ident.to_string(),
+        StatePattern::Multiple { states } => states
+            .iter()
+            .map(|(ident, _)| ident.to_string())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        StatePattern::Wildcard => "_".to_string(),
+    }
+}
+
+fn validate_wildcard_overlaps(transitions: &[Transition]) -> Result<()> {
+    let mut errors = Vec::new();
+
+    for (index, transition) in transitions.iter().enumerate() {
+        let is_wildcard = matches!(transition.states, StatePattern::Wildcard);
+
+        if is_wildcard {
+            if let Some(span) = transition.precedence {
+                errors.push(Error::new(
+                    span,
+                    "#[precedence] cannot be used on a wildcard transition\n\
+                     help: mark the specific transition that should win instead",
+                ));
+            }
+            continue;
+        }
+
+        let mut overlaps = false;
+
+        for event in &transition.events {
+            let wildcard = transitions
+                .iter()
+                .enumerate()
+                .find_map(|(other_index, other)| {
+                    if !matches!(other.states, StatePattern::Wildcard) {
+                        return None;
+                    }
+                    other
+                        .events
+                        .iter()
+                        .find(|other_event| *other_event == event)
+                        .map(|other_event| (other_index, other_event))
+                });
+
+            let Some((wildcard_index, wildcard_event)) = wildcard else {
+                continue;
+            };
+            overlaps = true;
+
+            if transition.precedence.is_some() {
+                continue;
+            }
+
+            let sources = describe_sources(&transition.states);
+            let problem = if wildcard_index < index {
+                format!(
+                    "transition '{} + {}' is shadowed by the earlier wildcard transition '_ + {}' and can never fire",
+                    sources, event, event
+                )
+            } else {
+                format!(
+                    "transition '{} + {}' overlaps the later wildcard transition '_ + {}'",
+                    sources, event, event
+                )
+            };
+
+            let mut error = Error::new(
+                event.span(),
+                format!(
+                    "{}\n\
+                     help: mark it `#[precedence]` so it takes priority over the wildcard, or remove one of the transitions",
+                    problem
+                ),
+            );
+            error.combine(Error::new(
+                wildcard_event.span(),
+                format!(
+                    "overlapping wildcard transition '_ + {}' defined here",
+                    event
+                ),
+            ));
+            errors.push(error);
+        }
 
-    if let Err(e) = validate_no_duplicate_transitions(&state_machine.transitions) {
-        return e.to_compile_error().into();
+        if let (Some(span), false) = (transition.precedence, overlaps) {
+            errors.push(Error::new(
+                span,
+                format!(
+                    "#[precedence] has no effect: no wildcard transition handles the events of '{} + ...'\n\
+                     help: remove the attribute",
+                    describe_sources(&transition.states)
+                ),
+            ));
+        }
     }
 
-    let state_name = if let Some(ref name) = state_machine.name {
-        Ident::new(&format!("{}State", name), name.span())
-    } else {
-        Ident::new("State", Span::call_site())
-    };
+    combine_errors(errors)
+}
 
-    let event_name = if let Some(ref name) = state_machine.name {
-        Ident::new(&format!("{}Event", name), name.span())
-    } else {
-        Ident::new("Event", Span::call_site())
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+fn similar_name<'a>(
+    name: &Ident,
+    candidates: impl IntoIterator<Item = &'a Ident>,
+) -> Option<&'a Ident> {
+    let name_str = name.to_string();
+    let max_distance = match name_str.chars().count() {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
     };
 
-    let mut all_states = alloc::vec::Vec::new();
-    let mut all_events = alloc::vec::Vec::new();
-    let mut initial_state = None;
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(&name_str, &candidate.to_string()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn did_you_mean(name: &Ident, candidates: &[Ident]) -> String {
+    similar_name(name, candidates)
+        .map(|candidate| format!("\nhelp: did you mean '{}'?", candidate))
+        .unwrap_or_default()
+}
+
+fn count_mentions(mentions: Vec<&Ident>) -> Vec<(&Ident, usize)> {
+    let mut counts: Vec<(&Ident, usize)> = Vec::new();
+    for mention in mentions {
+        match counts.iter_mut().find(|(ident, _)| *ident == mention) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((mention, 1)),
+        }
+    }
+    counts
+}
+
+fn validate_similar_names(state_machine: &StateMachine) -> Result<()> {
+    let mut state_mentions = Vec::new();
+    let mut event_mentions = Vec::new();
 
     for transition in &state_machine.transitions {
         match &transition.states {
-            StatePattern::Single { ident, initial } => {
-                if !all_states.iter().any(|s| s == ident) {
-                    all_states.push(ident.clone());
-                }
-                if *initial && initial_state.is_none() {
-                    initial_state = Some(ident.clone());
-                }
-            }
+            StatePattern::Single { ident, .. } => state_mentions.push(ident),
             StatePattern::Multiple { states } => {
-                for (ident, initial) in states {
-                    if !all_states.iter().any(|s| s == ident) {
-                        all_states.push(ident.clone());
-                    }
-                    if *initial && initial_state.is_none() {
-                        initial_state = Some(ident.clone());
-                    }
-                }
+                state_mentions.extend(states.iter().map(|(ident, _)| ident))
             }
             StatePattern::Wildcard => {}
         }
+        if let TargetState::State(target) = &transition.target {
+            state_mentions.push(target);
+        }
+        event_mentions.extend(&transition.events);
+    }
 
-        if let TargetState::State(ref target) = transition.target {
-            if !all_states.iter().any(|s| s == target) {
-                all_states.push(target.clone());
-            }
+    state_mentions.extend(&state_machine.initial);
+    state_mentions.extend(&state_machine.terminal);
+    state_mentions.extend(&state_machine.allow_unreachable);
+    for entry in &state_machine.ignore {
+        if let StatePattern::Single { ident, .. } = &entry.states {
+            state_mentions.push(ident);
+        } else if let StatePattern::Multiple { states } = &entry.states {
+            state_mentions.extend(states.iter().map(|(ident, _)| ident));
         }
+        event_mentions.extend(&entry.events);
+    }
 
-        for event in &transition.events {
-            if !all_events.iter().any(|e| e == event) {
-                all_events.push(event.clone());
-            }
+    let mut errors = Vec::new();
+
+    for allowed in &state_machine.allow_similar {
+        if !state_mentions.contains(&allowed) && !event_mentions.contains(&allowed) {
+            let known: Vec<Ident> = state_mentions
+                .iter()
+                .chain(&event_mentions)
+                .map(|ident| (*ident).clone())
+                .collect();
+            errors.push(Error::new(
+                allowed.span(),
+                format!(
+                    "unknown name '{}' in allow_similar\n\
+                     help: only states and events used in the transitions block can be listed{}",
+                    allowed,
+                    did_you_mean(allowed, &known)
+                ),
+            ));
         }
     }
 
-    let initial_state = initial_state.unwrap_or_else(|| {
-        Ident::new(
-            &all_states
-                .first()
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| "Initial".to_string()),
-            Span::call_site(),
-        )
-    });
+    for (kind, mentions) in [("state", state_mentions), ("event", event_mentions)] {
+        let counts = count_mentions(mentions);
+        let established: Vec<&Ident> = counts
+            .iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(ident, _)| *ident)
+            .collect();
 
-    let default_derives = vec![
-        Ident::new("Debug", Span::call_site()),
-        Ident::new("Clone", Span::call_site()),
-        Ident::new("PartialEq", Span::call_site()),
-        Ident::new("Eq", Span::call_site()),
-    ];
+        for (ident, _) in counts
+            .iter()
+            .filter(|(ident, count)| *count == 1 && !state_machine.allow_similar.contains(ident))
+        {
+            if let Some(intended) = similar_name(ident, established.iter().copied()) {
+                errors.push(Error::new(
+                    ident.span(),
+                    format!(
+                        "{} '{}' is used only once and looks like a misspelling of '{}'\n\
+                         help: did you mean '{}'?\n\
+                         note: a misspelled name silently creates a new {}; if '{}' is intended, list it in `allow_similar: [{}]`",
+                        kind, ident, intended, intended, kind, ident, ident
+                    ),
+                ));
+            }
+        }
+    }
 
-    let state_derives = state_machine
-        .derive_states
-        .as_ref()
-        .unwrap_or(&default_derives);
+    combine_errors(errors)
+}
 
-    let event_derives = state_machine
-        .derive_events
-        .as_ref()
-        .unwrap_or(&default_derives);
+fn resolve_initial_state(
+    initial: Option<&Ident>,
+    initial_markers: &[Ident],
+    all_states: &[Ident],
+) -> Result<Ident> {
+    match (initial, initial_markers) {
+        (Some(initial), []) => {
+            if !all_states.contains(initial) {
+                return Err(Error::new(
+                    initial.span(),
+                    format!(
+                        "unknown initial state '{}'\n\
+                         help: the initial state must be used in the transitions block{}",
+                        initial,
+                        did_you_mean(initial, all_states)
+                    ),
+                ));
+            }
+            Ok(initial.clone())
+        }
+        (Some(initial), [marker, ..]) => {
+            let mut error = Error::new(
+                initial.span(),
+                "initial state is declared with both `initial:` and a '*' marker\n\
+                 help: use only one of them",
+            );
+            error.combine(Error::new(
+                marker.span(),
+                format!("state '{}' is marked initial with '*' here", marker),
+            ));
+            Err(error)
+        }
+        (None, [marker]) => Ok(marker.clone()),
+        (None, [first, rest @ ..]) => {
+            let mut error = Error::new(
+                first.span(),
+                format!(
+                    "multiple initial states: '{}' is marked with '*' but so are {}\n\
+                     help: mark exactly one state with '*'",
+                    first,
+                    rest.iter()
+                        .map(|marker| format!("'{}'", marker))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+            for marker in rest {
+                error.combine(Error::new(
+                    marker.span(),
+                    format!("state '{}' is also marked initial here", marker),
+                ));
+            }
+            Err(error)
+        }
+        (None, []) => Err(Error::new(
+            Span::call_site(),
+            "no initial state\n\
+             help: mark one state with '*', such as `*Idle + Start = Running`, or add `initial: Idle`",
+        )),
+    }
+}
 
-    let state_enum = quote! {
-        #[derive(#(#state_derives),*)]
-        pub enum #state_name {
-            #(#all_states),*
+fn validate_reachability(
+    all_states: &[Ident],
+    initial_state: &Ident,
+    terminal: &[Ident],
+    transitions: &[Transition],
+    allow_unreachable: &[Ident],
+) -> Result<()> {
+    for allowed in allow_unreachable {
+        if !all_states.contains(allowed) {
+            return Err(Error::new(
+                allowed.span(),
+                format!(
+                    "unknown state '{}' in allow_unreachable\n\
+                     help: only states used in the transitions block can be listed{}",
+                    allowed,
+                    did_you_mean(allowed, all_states)
+                ),
+            ));
         }
-    };
+    }
 
-    let event_enum_variants = all_events.iter().map(|event| {
-        quote! { #event }
-    });
+    let edges = expand_edges(transitions, all_states, terminal);
+    let mut reachable: Vec<&Ident> = vec![initial_state];
+    reachable.extend(allow_unreachable);
 
-    let event_enum = quote! {
-        #[derive(#(#event_derives),*)]
-        pub enum #event_name {
-            #(#event_enum_variants),*
+    let mut index = 0;
+    while index < reachable.len() {
+        let current = reachable[index];
+        for (source, _, target) in &edges {
+            if *source == current && !reachable.contains(target) {
+                reachable.push(target);
+            }
         }
-    };
+        index += 1;
+    }
 
-    let mut transition_checks = TokenStream2::new();
+    let errors = all_states
+        .iter()
+        .filter(|state| !reachable.contains(state))
+        .map(|state| {
+            Error::new(
+                state.span(),
+                format!(
+                    "unreachable state: '{}' cannot be entered from the initial state '{}'{}\n\
+                     help: add a transition into '{}' or remove its transitions\n\
+                     note: if the state is entered another way, such as deserialization, list it in `allow_unreachable: [{}]`",
+                    state,
+                    initial_state,
+                    did_you_mean(state, all_states),
+                    state,
+                    state
+                ),
+            )
+        });
 
-    for transition in &state_machine.transitions {
-        let events = &transition.events;
+    combine_errors(errors)
+}
+
+fn validate_terminal_states(
+    all_states: &[Ident],
+    transitions: &[Transition],
+    terminal: &[Ident],
+) -> Result<()> {
+    for state in terminal {
+        if !all_states.contains(state) {
+            return Err(Error::new(
+                state.span(),
+                format!(
+                    "unknown state '{}' in terminal\n\
+                     help: only states used in the transitions block can be listed{}",
+                    state,
+                    did_you_mean(state, all_states)
+                ),
+            ));
+        }
+    }
 
-        let target_state = match &transition.target {
-            TargetState::State(state) => quote! { #state_name::#state },
-            TargetState::Internal => quote! { self.clone() },
+    for transition in transitions {
+        let explicit_sources: Vec<&Ident> = match &transition.states {
+            StatePattern::Single { ident, .. } => vec![ident],
+            StatePattern::Multiple { states } => states.iter().map(|(ident, _)| ident).collect(),
+            StatePattern::Wildcard => continue,
         };
 
-        let state_patterns: Vec<_> = match &transition.states {
-            StatePattern::Single { ident, .. } => {
-                alloc::vec![quote! { #state_name::#ident }]
-            }
-            StatePattern::Multiple { states } => states
-                .iter()
-                .map(|(ident, _)| quote! { #state_name::#ident })
-                .collect(),
-            StatePattern::Wildcard => {
-                alloc::vec![quote! { _ }]
+        for source in explicit_sources {
+            if terminal.contains(source) {
+                return Err(Error::new(
+                    source.span(),
+                    format!(
+                        "terminal state '{}' has outgoing transitions\n\
+                         help: remove '{}' from terminal or remove its transitions",
+                        source, source
+                    ),
+                ));
             }
-        };
+        }
+    }
 
-        let state_condition = if state_patterns.len() == 1 && state_patterns[0].to_string() == "_" {
-            quote! { true }
-        } else if state_patterns.len() == 1 {
-            let pattern = &state_patterns[0];
-            quote! { matches!(*self, #pattern) }
-        } else {
-            quote! { #(matches!(*self, #state_patterns))||* }
-        };
+    let edges = expand_edges(transitions, all_states, terminal);
+    let errors = all_states
+        .iter()
+        .filter(|state| !terminal.contains(state))
+        .filter(|state| !edges.iter().any(|(source, _, _)| source == state))
+        .map(|state| {
+            Error::new(
+                state.span(),
+                format!(
+                    "state '{}' has no outgoing transitions; declare it terminal or add transitions{}\n\
+                     help: add `terminal: [{}]` if the machine is meant to stop here",
+                    state,
+                    did_you_mean(state, all_states),
+                    state
+                ),
+            )
+        });
 
-        for event in events {
-            let event_condition = quote! { matches!(event, #event_name::#event) };
+    combine_errors(errors)
+}
 
-            transition_checks.extend(quote! {
-                if #state_condition && #event_condition {
-                    return ::core::option::Option::Some(#target_state);
-                }
-            });
-        }
+fn ordered_transitions(transitions: &[Transition]) -> impl Iterator<Item = &Transition> {
+    let specific = transitions
+        .iter()
+        .filter(|transition| !matches!(transition.states, StatePattern::Wildcard));
+    let wildcard = transitions
+        .iter()
+        .filter(|transition| matches!(transition.states, StatePattern::Wildcard));
+    specific.chain(wildcard)
+}
+
+fn validate_exhaustive(
+    all_states: &[Ident],
+    all_events: &[Ident],
+    terminal: &[Ident],
+    exhaustive: Option<&LitBool>,
+    ignore: &[IgnoredEvents],
+    transitions: &[Transition],
+) -> Result<()> {
+    let enabled = exhaustive.is_some_and(|exhaustive| exhaustive.value);
+
+    if let (false, Some(first)) = (enabled, ignore.first()) {
+        let span = first.events[0].span();
+        return Err(Error::new(
+            span,
+            "ignore has no effect without `exhaustive: true`\n\
+             help: add `exhaustive: true` or remove the ignore block",
+        ));
     }
 
-    let expanded = quote! {
-        #state_enum
-        #event_enum
+    let edges = expand_edges(transitions, all_states, terminal);
+    let mut ignored = Vec::new();
 
-        impl ::core::default::Default for #state_name {
-            fn default() -> Self {
-                #state_name::#initial_state
+    for entry in ignore {
+        let states: Vec<&Ident> = match &entry.states {
+            StatePattern::Single {
+                ident,
+                initial: false,
+            } => vec![ident],
+            StatePattern::Multiple { states } if states.iter().all(|(_, initial)| !initial) => {
+                states.iter().map(|(ident, _)| ident).collect()
             }
-        }
+            StatePattern::Wildcard => all_states.iter().collect(),
+            _ => {
+                return Err(Error::new(
+                    entry.events[0].span(),
+                    "the initial state marker '*' is not allowed in ignore",
+                ))
+            }
+        };
 
-        impl #state_name {
-            pub fn process_event(&self, event: #event_name) -> ::core::option::Option<#state_name> {
-                #transition_checks
-                ::core::option::Option::None
+        for state in &states {
+            if !all_states.contains(state) {
+                return Err(Error::new(
+                    state.span(),
+                    format!(
+                        "unknown state '{}' in ignore\n\
+                         help: only states used in the transitions block can be listed{}",
+                        state,
+                        did_you_mean(state, all_states)
+                    ),
+                ));
             }
         }
+
+        for event in &entry.events {
+            if !all_events.contains(event) {
+                return Err(Error::new(
+                    event.span(),
+                    format!(
+                        "unknown event '{}' in ignore\n\
+                         help: only events used in the transitions block can be listed{}",
+                        event,
+                        did_you_mean(event, all_events)
+                    ),
+                ));
+            }
+
+            let wildcard = matches!(entry.states, StatePattern::Wildcard);
+            for state in &states {
+                let handled = edges
+                    .iter()
+                    .any(|(source, handled, _)| source == state && *handled == event);
+                if handled && wildcard {
+                    continue;
+                } else if handled {
+                    return Err(Error::new(
+                        event.span(),
+                        format!(
+                            "state '{}' + event '{}' is ignored but also has a transition\n\
+                             help: remove it from ignore or remove the transition",
+                            state, event
+                        ),
+                    ));
+                }
+                ignored.push((*state, event));
+            }
+        }
+    }
+
+    let Some(exhaustive) = exhaustive.filter(|_| enabled) else {
+        return Ok(());
+    };
+
+    let mut missing = Vec::new();
+    for state in all_states.iter().filter(|state| !terminal.contains(state)) {
+        for event in all_events {
+            let handled = edges
+                .iter()
+                .any(|(source, handled, _)| *source == state && *handled == event);
+            if !handled && !ignored.contains(&(state, event)) {
+                missing.push(format!("    {} + {}", state, event));
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        exhaustive.span(),
+        format!(
+            "exhaustive state machine is missing {} state + event combination(s):\n{}\n\
+             help: add a transition for each combination or list it in `ignore: {{ ... }}`",
+            missing.len(),
+            missing.join("\n")
+        ),
+    ))
+}
+
+fn assign_discriminants(
+    kind: &str,
+    repr: Option<&Ident>,
+    names: &[Ident],
+    declared: &[DeclaredVariant],
+) -> Result<Option<Vec<u64>>> {
+    let max = match repr {
+        Some(repr) if repr == "u8" => u64::from(u8::MAX),
+        _ => u64::from(u16::MAX),
+    };
+    let mut explicit: Vec<(&Ident, u64, &LitInt)> = Vec::new();
+    for (index, variant) in declared.iter().enumerate() {
+        if !names.contains(&variant.ident) {
+            return Err(Error::new(
+                variant.ident.span(),
+                format!(
+                    "unknown {} '{}' in `{}s` block\n\
+                     help: only {}s used in the transitions block can be listed{}",
+                    kind,
+                    variant.ident,
+                    kind,
+                    kind,
+                    did_you_mean(&variant.ident, names)
+                ),
+            ));
+        }
+        if declared[..index]
+            .iter()
+            .any(|previous| previous.ident == variant.ident)
+        {
+            return Err(Error::new(
+                variant.ident.span(),
+                format!("{} '{}' is listed more than once", kind, variant.ident),
+            ));
+        }
+
+        let Some(discriminant) = &variant.discriminant else {
+            continue;
+        };
+        let Some(repr) = repr else {
+            return Err(Error::new(
+                discriminant.span(),
+                "explicit discriminants require a `repr`\n\
+                 help: add `repr: u8` or `repr: u16`",
+            ));
+        };
+        let value = discriminant.base10_parse::<u64>()?;
+        if value > max {
+            return Err(Error::new(
+                discriminant.span(),
+                format!("discriminant {} does not fit in `{}`", value, repr),
+            ));
+        }
+        if let Some((other, _, other_discriminant)) = explicit
+            .iter()
+            .find(|(_, other_value, _)| *other_value == value)
+        {
+            let mut error = Error::new(
+                discriminant.span(),
+                format!(
+                    "discriminant {} is assigned to both '{}' and '{}'",
+                    value, other, variant.ident
+                ),
+            );
+            error.combine(Error::new(
+                other_discriminant.span(),
+                format!("'{}' is assigned {} here", other, value),
+            ));
+            return Err(error);
+        }
+        explicit.push((&variant.ident, value, discriminant));
+    }
+
+    let Some(repr) = repr else {
+        return Ok(None);
+    };
+
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !declared.iter().any(|variant| variant.ident == **name))
+    {
+        return Err(Error::new(
+            missing.span(),
+            format!(
+                "{} '{}' has no discriminant\n\
+                 help: list '{}' in the `{}s` block\n\
+                 note: with `repr`, {}s without an explicit value are numbered in `{}s` block order, so appending new ones never renumbers existing ones",
+                kind, missing, missing, kind, kind, kind
+            ),
+        ));
+    }
+
+    let mut assigned: Vec<(&Ident, u64)> = Vec::new();
+    let mut next = 0;
+    for variant in declared {
+        let value = match explicit
+            .iter()
+            .find(|(ident, _, _)| **ident == variant.ident)
+        {
+            Some((_, value, _)) => *value,
+            None => next,
+        };
+        if value > max {
+            return Err(Error::new(
+                variant.ident.span(),
+                format!(
+                    "discriminant {} of '{}' does not fit in `{}`",
+                    value, variant.ident, repr
+                ),
+            ));
+        }
+        if let Some((other, _)) = assigned
+            .iter()
+            .find(|(_, other_value)| *other_value == value)
+        {
+            let implicit = if variant.discriminant.is_some() {
+                *other
+            } else {
+                &variant.ident
+            };
+            return Err(Error::new(
+                implicit.span(),
+                format!(
+                    "discriminant {} is assigned to both '{}' and '{}'\n\
+                     help: give '{}' an explicit discriminant",
+                    value, other, variant.ident, implicit
+                ),
+            ));
+        }
+        assigned.push((&variant.ident, value));
+        next = value + 1;
+    }
+
+    Ok(Some(
+        names
+            .iter()
+            .map(|name| {
+                assigned
+                    .iter()
+                    .find(|(ident, _)| *ident == name)
+                    .map(|(_, value)| *value)
+                    .unwrap()
+            })
+            .collect(),
+    ))
+}
+
+fn is_fieldless(declared: &[DeclaredVariant], name: &Ident) -> bool {
+    declared
+        .iter()
+        .find(|variant| variant.ident == *name)
+        .is_none_or(|variant| matches!(variant.fields, syn::Fields::Unit))
+}
+
+fn carries_data(declared: &[DeclaredVariant]) -> bool {
+    declared
+        .iter()
+        .any(|variant| !matches!(variant.fields, syn::Fields::Unit))
+}
+
+fn mentions(tokens: TokenStream2, param: &GenericParam) -> bool {
+    let (name, lifetime) = match param {
+        GenericParam::Type(param) => (&param.ident, false),
+        GenericParam::Lifetime(param) => (&param.lifetime.ident, true),
+        GenericParam::Const(param) => (&param.ident, false),
+    };
+    let mut after_apostrophe = false;
+    for tree in tokens {
+        let found = match &tree {
+            TokenTree::Group(group) => mentions(group.stream(), param),
+            TokenTree::Ident(ident) => ident == name && after_apostrophe == lifetime,
+            _ => false,
+        };
+        if found {
+            return true;
+        }
+        after_apostrophe = matches!(&tree, TokenTree::Punct(punct) if punct.as_char() == '\'');
+    }
+    false
+}
+
+fn subset_generics(
+    params: Punctuated<GenericParam, Comma>,
+    predicates: impl IntoIterator<Item = WherePredicate>,
+) -> Generics {
+    Generics {
+        lt_token: Some(Default::default()),
+        params,
+        gt_token: Some(Default::default()),
+        where_clause: Some(syn::WhereClause {
+            where_token: Default::default(),
+            predicates: predicates.into_iter().collect(),
+        }),
+    }
+}
+
+fn payload_generics(generics: &Generics, declared: &[DeclaredVariant]) -> Generics {
+    let fields: TokenStream2 = declared
+        .iter()
+        .map(|variant| variant.fields.to_token_stream())
+        .collect();
+    let params: Punctuated<GenericParam, Comma> = generics
+        .params
+        .iter()
+        .filter(|param| mentions(fields.clone(), param))
+        .cloned()
+        .collect();
+    let predicates = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter())
+        .filter(|predicate| {
+            generics
+                .params
+                .iter()
+                .filter(|param| mentions(predicate.to_token_stream(), param))
+                .all(|param| params.iter().any(|kept| kept == param))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    subset_generics(params, predicates)
+}
+
+fn method_generics(generics: &Generics, state_generics: &Generics) -> Generics {
+    let params = generics
+        .params
+        .iter()
+        .filter(|param| !state_generics.params.iter().any(|kept| kept == *param))
+        .cloned()
+        .collect();
+    let state_predicates = &state_generics.where_clause.as_ref().unwrap().predicates;
+    let predicates = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter())
+        .filter(|predicate| !state_predicates.iter().any(|kept| kept == *predicate))
+        .cloned();
+    subset_generics(params, predicates)
+}
+
+fn variant_pattern(enum_name: &Ident, name: &Ident, declared: &[DeclaredVariant]) -> TokenStream2 {
+    let fields = declared
+        .iter()
+        .find(|variant| variant.ident == *name)
+        .map(|variant| &variant.fields);
+    match fields {
+        Some(syn::Fields::Named(_)) => quote! { #enum_name::#name { .. } },
+        Some(syn::Fields::Unnamed(_)) => quote! { #enum_name::#name(..) },
+        _ => quote! { #enum_name::#name },
+    }
+}
+
+fn enum_variants(
+    names: &[Ident],
+    declared: &[DeclaredVariant],
+    discriminants: Option<&[u64]>,
+) -> Vec<TokenStream2> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let fields = declared
+                .iter()
+                .find(|variant| variant.ident == *name)
+                .map(|variant| &variant.fields);
+            match discriminants {
+                Some(values) => {
+                    let value = proc_macro2::Literal::u64_unsuffixed(values[index]);
+                    quote! { #name #fields = #value }
+                }
+                None => quote! { #name #fields },
+            }
+        })
+        .collect()
+}
+
+fn pending_name(state_name: &Ident) -> Ident {
+    Ident::new(&format!("{}Pending", state_name), state_name.span())
+}
+
+fn builder_name(state_name: &Ident, target: &Ident) -> Ident {
+    Ident::new(&format!("{}{}Builder", state_name, target), target.span())
+}
+
+fn next_state(
+    state_name: &Ident,
+    declared_states: &[DeclaredVariant],
+    target: &Ident,
+    internal: bool,
+) -> TokenStream2 {
+    if !carries_data(declared_states) {
+        return quote! { ::core::option::Option::Some(#state_name::#target) };
+    }
+
+    let pending = pending_name(state_name);
+    if internal {
+        quote! { ::core::option::Option::Some(#pending::Stay) }
+    } else if is_fieldless(declared_states, target) {
+        quote! { ::core::option::Option::Some(#pending::Ready(#state_name::#target)) }
+    } else {
+        let builder = builder_name(state_name, target);
+        quote! { ::core::option::Option::Some(#pending::#target(#builder(()))) }
+    }
+}
+
+fn generate_pending(
+    state_name: &Ident,
+    generics: &Generics,
+    state_derives: &[Ident],
+    declared_states: &[DeclaredVariant],
+    transitions: &[Transition],
+) -> Result<TokenStream2> {
+    let pending = pending_name(state_name);
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    let targets: Vec<&DeclaredVariant> = declared_states
+        .iter()
+        .filter(|variant| !matches!(variant.fields, syn::Fields::Unit))
+        .filter(|variant| {
+            transitions.iter().any(|transition| {
+                matches!(&transition.target, TargetState::State(target) if *target == variant.ident)
+            })
+        })
+        .collect();
+
+    if let Some(variant) = targets
+        .iter()
+        .find(|variant| variant.ident == "Stay" || variant.ident == "Ready")
+    {
+        return Err(Error::new(
+            variant.ident.span(),
+            format!(
+                "state '{}' carries data and clashes with `{}::{}`\n\
+                 help: rename the state",
+                variant.ident, pending, variant.ident
+            ),
+        ));
+    }
+
+    let mut builders = TokenStream2::new();
+    let mut variants = Vec::new();
+    for variant in targets {
+        let target = &variant.ident;
+        let builder = builder_name(state_name, target);
+        let (parameters, construct) = match &variant.fields {
+            syn::Fields::Named(fields) => {
+                let names: Vec<&Ident> = fields
+                    .named
+                    .iter()
+                    .map(|field| field.ident.as_ref().unwrap())
+                    .collect();
+                let types = fields.named.iter().map(|field| &field.ty);
+                (
+                    quote! { #(#names: #types),* },
+                    quote! { #state_name::#target { #(#names),* } },
+                )
+            }
+            syn::Fields::Unnamed(fields) => {
+                let names: Vec<Ident> = (0..fields.unnamed.len())
+                    .map(|index| Ident::new(&format!("field{}", index), target.span()))
+                    .collect();
+                let types = fields.unnamed.iter().map(|field| &field.ty);
+                (
+                    quote! { #(#names: #types),* },
+                    quote! { #state_name::#target(#(#names),*) },
+                )
+            }
+            syn::Fields::Unit => unreachable!(),
+        };
+        builders.extend(quote! {
+            #[derive(#(#state_derives),*)]
+            pub struct #builder(());
+
+            impl #builder {
+                pub fn build #impl_generics (self, #parameters) -> #state_name #type_generics #where_clause {
+                    #construct
+                }
+            }
+        });
+        variants.push(quote! { #target(#builder) });
+    }
+
+    Ok(quote! {
+        #[must_use]
+        #[derive(#(#state_derives),*)]
+        pub enum #pending #generics #where_clause {
+            Stay,
+            Ready(#state_name #type_generics),
+            #(#variants,)*
+        }
+
+        #builders
+    })
+}
+
+fn generate_enumeration(
+    enum_name: &Ident,
+    generics: &Generics,
+    names: &[Ident],
+    declared: &[DeclaredVariant],
+) -> TokenStream2 {
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    let count = names.len();
+    let indices = (0..count).map(proc_macro2::Literal::usize_unsuffixed);
+    let patterns = names
+        .iter()
+        .map(|name| variant_pattern(enum_name, name, declared));
+    let all = (!carries_data(declared)).then(|| {
+        quote! {
+            pub const ALL: [#enum_name; #count] = [#(#enum_name::#names),*];
+        }
+    });
+
+    quote! {
+        impl #impl_generics #enum_name #type_generics #where_clause {
+            pub const COUNT: usize = #count;
+            #all
+
+            pub const fn index(&self) -> usize {
+                match self {
+                    #(#patterns => #indices,)*
+                }
+            }
+        }
+    }
+}
+
+fn generate_transition_info(
+    info_name: &Ident,
+    state_name: &Ident,
+    event_name: &Ident,
+    transitions: &[Transition],
+) -> TokenStream2 {
+    let mut entries = Vec::new();
+    for transition in transitions {
+        let sources = match &transition.states {
+            StatePattern::Single { ident, .. } => vec![quote! {
+                ::core::option::Option::Some(#state_name::#ident)
+            }],
+            StatePattern::Multiple { states } => states
+                .iter()
+                .map(|(ident, _)| quote! { ::core::option::Option::Some(#state_name::#ident) })
+                .collect(),
+            StatePattern::Wildcard => vec![quote! { ::core::option::Option::None }],
+        };
+        let target = match &transition.target {
+            TargetState::State(target) => {
+                quote! { ::core::option::Option::Some(#state_name::#target) }
+            }
+            TargetState::Internal => quote! { ::core::option::Option::None },
+        };
+        let line = transition.line as u32;
+        for source in &sources {
+            for event in &transition.events {
+                entries.push(quote! {
+                    #info_name {
+                        source: #source,
+                        event: #event_name::#event,
+                        target: #target,
+                        line: #line,
+                    }
+                });
+            }
+        }
+    }
+
+    quote! {
+        pub struct #info_name {
+            pub source: ::core::option::Option<#state_name>,
+            pub event: #event_name,
+            pub target: ::core::option::Option<#state_name>,
+            pub line: u32,
+        }
+
+        impl #state_name {
+            pub const TRANSITIONS: &'static [#info_name] = &[#(#entries),*];
+        }
+    }
+}
+
+fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for (index, c) in chars.iter().enumerate() {
+        if *c == '_' {
+            if !word.is_empty() {
+                words.push(core::mem::take(&mut word));
+            }
+            continue;
+        }
+        let boundary = c.is_uppercase()
+            && index > 0
+            && (chars[index - 1].is_lowercase()
+                || chars[index - 1].is_ascii_digit()
+                || (chars[index - 1].is_uppercase()
+                    && chars.get(index + 1).is_some_and(|next| next.is_lowercase())));
+        if boundary && !word.is_empty() {
+            words.push(core::mem::take(&mut word));
+        }
+        word.push(*c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn rename(name: &Ident, case: Option<&Ident>) -> String {
+    let name = name.to_string();
+    let Some(case) = case else {
+        return name;
+    };
+    let words = split_words(&name);
+    let capitalize = |word: &String| {
+        let mut chars = word.chars();
+        chars.next().map_or(String::new(), |first| {
+            first
+                .to_uppercase()
+                .chain(chars.flat_map(char::to_lowercase))
+                .collect()
+        })
+    };
+    let lower: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+    let upper: Vec<String> = words.iter().map(|word| word.to_uppercase()).collect();
+
+    if case == "PascalCase" {
+        words.iter().map(capitalize).collect()
+    } else if case == "camelCase" {
+        let mut renamed = lower.first().cloned().unwrap_or_default();
+        renamed.extend(words.iter().skip(1).map(capitalize));
+        renamed
+    } else if case == "snake_case" {
+        lower.join("_")
+    } else if case == "SCREAMING_SNAKE_CASE" {
+        upper.join("_")
+    } else if case == "lowercase" {
+        lower.concat()
+    } else {
+        upper.concat()
+    }
+}
+
+fn generate_string_impls(
+    enum_name: &Ident,
+    generics: &Generics,
+    names: &[Ident],
+    declared: &[DeclaredVariant],
+    rename_all: Option<&Ident>,
+) -> Result<TokenStream2> {
+    let strings: Vec<String> = names.iter().map(|name| rename(name, rename_all)).collect();
+    for (index, string) in strings.iter().enumerate() {
+        if let Some(other) = strings[..index].iter().position(|other| other == string) {
+            return Err(Error::new(
+                names[index].span(),
+                format!(
+                    "'{}' and '{}' both render as \"{}\"\n\
+                     help: rename one of them or choose a different `rename_all`",
+                    names[other], names[index], string
+                ),
+            ));
+        }
+    }
+
+    let patterns: Vec<TokenStream2> = names
+        .iter()
+        .map(|name| variant_pattern(enum_name, name, declared))
+        .collect();
+    let error_name = Ident::new(&format!("Parse{}Error", enum_name), enum_name.span());
+    let message = format!(
+        "invalid {}, expected one of: {}",
+        enum_name,
+        strings.join(", ")
+    );
+    let from_str = (!carries_data(declared))
+        .then(|| generate_from_str(enum_name, &error_name, names, &strings, &message));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #enum_name #type_generics #where_clause {
+            pub const fn as_str(&self) -> &'static str {
+                match self {
+                    #(#patterns => #strings,)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Display for #enum_name #type_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        #from_str
+    })
+}
+
+fn generate_from_str(
+    enum_name: &Ident,
+    error_name: &Ident,
+    names: &[Ident],
+    strings: &[String],
+    message: &str,
+) -> TokenStream2 {
+    quote! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct #error_name;
+
+        impl #error_name {
+            pub const VALID_NAMES: &'static [&'static str] = &[#(#strings),*];
+        }
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(#message)
+            }
+        }
+
+        impl ::core::error::Error for #error_name {}
+
+        impl ::core::str::FromStr for #enum_name {
+            type Err = #error_name;
+
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                match s {
+                    #(#strings => ::core::result::Result::Ok(#enum_name::#names),)*
+                    _ => ::core::result::Result::Err(#error_name),
+                }
+            }
+        }
+    }
+}
+
+fn generate_discriminant_impls(
+    enum_name: &Ident,
+    generics: &Generics,
+    repr: &Ident,
+    names: &[Ident],
+    declared: &[DeclaredVariant],
+    discriminants: &[u64],
+) -> TokenStream2 {
+    let error_name = Ident::new(&format!("TryFrom{}Error", enum_name), enum_name.span());
+    let as_repr = Ident::new(&format!("as_{}", repr), repr.span());
+    let values: Vec<_> = discriminants
+        .iter()
+        .map(|value| proc_macro2::Literal::u64_unsuffixed(*value))
+        .collect();
+    let message = format!("invalid {} discriminant: {{}}", enum_name);
+    let patterns = names
+        .iter()
+        .map(|name| variant_pattern(enum_name, name, declared));
+
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    let as_repr = quote! {
+        impl #impl_generics #enum_name #type_generics #where_clause {
+            pub const fn #as_repr(&self) -> #repr {
+                match self {
+                    #(#patterns => #values,)*
+                }
+            }
+        }
+    };
+    if carries_data(declared) {
+        return as_repr;
+    }
+
+    quote! {
+        #as_repr
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct #error_name(pub #repr);
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                write!(f, #message, self.0)
+            }
+        }
+
+        impl ::core::error::Error for #error_name {}
+
+        impl ::core::convert::TryFrom<#repr> for #enum_name {
+            type Error = #error_name;
+
+            fn try_from(value: #repr) -> ::core::result::Result<Self, Self::Error> {
+                match value {
+                    #(#values => ::core::result::Result::Ok(#enum_name::#names),)*
+                    _ => ::core::result::Result::Err(#error_name(value)),
+                }
+            }
+        }
+    }
+}
+
+fn expand_sources<'a>(
+    pattern: &'a StatePattern,
+    all_states: &'a [Ident],
+    terminal: &[Ident],
+) -> Vec<&'a Ident> {
+    match pattern {
+        StatePattern::Single { ident, .. } => vec![ident],
+        StatePattern::Multiple { states } => states.iter().map(|(ident, _)| ident).collect(),
+        StatePattern::Wildcard => all_states
+            .iter()
+            .filter(|state| !terminal.contains(state))
+            .collect(),
+    }
+}
+
+fn expand_edges<'a>(
+    transitions: &'a [Transition],
+    all_states: &'a [Ident],
+    terminal: &[Ident],
+) -> Vec<(&'a Ident, &'a Ident, &'a Ident)> {
+    let mut edges = Vec::new();
+
+    for transition in ordered_transitions(transitions) {
+        for source in expand_sources(&transition.states, all_states, terminal) {
+            let target = match &transition.target {
+                TargetState::State(target) => target,
+                TargetState::Internal => source,
+            };
+            for event in &transition.events {
+                if !edges.iter().any(|(s, e, _)| *s == source && *e == event) {
+                    edges.push((source, event, target));
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+fn weighted_edges<'a>(
+    all_states: &'a [Ident],
+    terminal: &[Ident],
+    transitions: &'a [Transition],
+) -> Vec<(usize, &'a Ident, usize, u64)> {
+    let index = |state: &Ident| all_states.iter().position(|s| s == state).unwrap();
+    expand_edges(transitions, all_states, terminal)
+        .into_iter()
+        .map(|(source, event, target)| {
+            let weight = ordered_transitions(transitions)
+                .find(|transition| {
+                    transition.events.contains(event)
+                        && expand_sources(&transition.states, all_states, terminal)
+                            .contains(&source)
+                })
+                .map_or(1, |transition| transition.weight);
+            (index(source), event, index(target), weight)
+        })
+        .collect()
+}
+
+fn shortest_paths<'a>(
+    start: usize,
+    state_count: usize,
+    edges: &[(usize, &'a Ident, usize, u64)],
+) -> Vec<Option<Vec<&'a Ident>>> {
+    let mut distances: Vec<Option<u64>> = vec![None; state_count];
+    let mut previous: Vec<Option<(usize, &Ident)>> = vec![None; state_count];
+    let mut visited = vec![false; state_count];
+    distances[start] = Some(0);
+
+    while let Some(current) = (0..state_count)
+        .filter(|index| !visited[*index])
+        .filter_map(|index| distances[index].map(|distance| (index, distance)))
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(index, _)| index)
+    {
+        visited[current] = true;
+        let distance = distances[current].unwrap();
+        for (source, event, target, weight) in edges {
+            if *source != current {
+                continue;
+            }
+            let candidate = distance.saturating_add(*weight);
+            if distances[*target].is_none_or(|existing| candidate < existing) {
+                distances[*target] = Some(candidate);
+                previous[*target] = Some((current, event));
+            }
+        }
+    }
+
+    (0..state_count)
+        .map(|target| {
+            distances[target]?;
+            let mut path = Vec::new();
+            let mut current = target;
+            while let Some((source, event)) = previous[current] {
+                path.push(event);
+                current = source;
+            }
+            path.reverse();
+            Some(path)
+        })
+        .collect()
+}
+
+fn generate_path_to(
+    state_name: &Ident,
+    event_name: &Ident,
+    all_states: &[Ident],
+    terminal: &[Ident],
+    declared_states: &[DeclaredVariant],
+    transitions: &[Transition],
+) -> TokenStream2 {
+    let pattern = |state: &Ident| variant_pattern(state_name, state, declared_states);
+    let edges = weighted_edges(all_states, terminal, transitions);
+    let mut arms = TokenStream2::new();
+    let mut unreachable = false;
+    for (start, source) in all_states.iter().enumerate() {
+        for (target, path) in all_states
+            .iter()
+            .zip(shortest_paths(start, all_states.len(), &edges))
+        {
+            let (source, target) = (pattern(source), pattern(target));
+            match path {
+                Some(events) => arms.extend(quote! {
+                    (#source, #target) => {
+                        ::core::option::Option::Some(&[#(#event_name::#events),*])
+                    }
+                }),
+                None => unreachable = true,
+            }
+        }
+    }
+    if unreachable {
+        arms.extend(quote! { _ => ::core::option::Option::None, });
+    }
+
+    quote! {
+        match (self, target) {
+            #arms
+        }
+    }
+}
+
+fn generate_mermaid(
+    all_states: &[Ident],
+    initial_state: &Ident,
+    terminal: &[Ident],
+    transitions: &[Transition],
+) -> String {
+    let mut mermaid = String::new();
+    let _ = writeln!(mermaid, "stateDiagram-v2");
+    let _ = writeln!(mermaid, "    [*] --> {}", initial_state);
+
+    for (source, event, target) in expand_edges(transitions, all_states, terminal) {
+        let _ = writeln!(mermaid, "    {} --> {}: {}", source, target, event);
+    }
+
+    for state in terminal {
+        let _ = writeln!(mermaid, "    {} --> [*]", state);
+    }
+
+    mermaid
+}
+
+fn generate_plantuml(
+    state_name: &Ident,
+    all_states: &[Ident],
+    initial_state: &Ident,
+    terminal: &[Ident],
+    transitions: &[Transition],
+) -> String {
+    let mut plantuml = String::new();
+    let _ = writeln!(plantuml, "@startuml");
+    let _ = writeln!(plantuml, "title {}", state_name);
+
+    for state in all_states {
+        let _ = writeln!(plantuml, "state {}", state);
+    }
+
+    let _ = writeln!(plantuml, "[*] --> {}", initial_state);
+
+    for (source, event, target) in expand_edges(transitions, all_states, terminal) {
+        let _ = writeln!(plantuml, "{} --> {} : {}", source, target, event);
+    }
+
+    for state in terminal {
+        let _ = writeln!(plantuml, "{} --> [*]", state);
+    }
+
+    let _ = writeln!(plantuml, "@enduml");
+    plantuml
+}
+
+fn generate_scxml(
+    name: Option<&Ident>,
+    all_states: &[Ident],
+    initial_state: &Ident,
+    terminal: &[Ident],
+    transitions: &[Transition],
+) -> String {
+    let mut scxml = String::new();
+    let _ = writeln!(scxml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let name_attribute = name
+        .map(|name| format!(" name=\"{}\"", name))
+        .unwrap_or_default();
+    let _ = writeln!(
+        scxml,
+        "<scxml xmlns=\"http://www.w3.org/2005/07/scxml\" version=\"1.0\"{} initial=\"{}\">",
+        name_attribute, initial_state
+    );
+
+    for state in all_states {
+        let mut handled: Vec<&Ident> = Vec::new();
+        let mut outgoing = Vec::new();
+
+        for transition in ordered_transitions(transitions) {
+            if !expand_sources(&transition.states, all_states, terminal).contains(&state) {
+                continue;
+            }
+            let events: Vec<&Ident> = transition
+                .events
+                .iter()
+                .filter(|event| !handled.contains(event))
+                .collect();
+            if events.is_empty() {
+                continue;
+            }
+            handled.extend(&events);
+            outgoing.push((events, &transition.target));
+        }
+
+        if outgoing.is_empty() {
+            let element = if terminal.contains(state) {
+                "final"
+            } else {
+                "state"
+            };
+            let _ = writeln!(scxml, "  <{} id=\"{}\"/>", element, state);
+            continue;
+        }
+
+        let _ = writeln!(scxml, "  <state id=\"{}\">", state);
+        for (events, target) in outgoing {
+            let events = events
+                .iter()
+                .map(|event| event.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            match target {
+                TargetState::State(target) => {
+                    let _ = writeln!(
+                        scxml,
+                        "    <transition event=\"{}\" target=\"{}\"/>",
+                        events, target
+                    );
+                }
+                TargetState::Internal => {
+                    let _ = writeln!(scxml, "    <transition event=\"{}\"/>", events);
+                }
+            }
+        }
+        let _ = writeln!(scxml, "  </state>");
+    }
+
+    let _ = writeln!(scxml, "</scxml>");
+    scxml
+}
+
+fn generate_transition_match(
+    state_name: &Ident,
+    event_name: &Ident,
+    all_states: &[Ident],
+    all_events: &[Ident],
+    state_machine: &StateMachine,
+) -> TokenStream2 {
+    let declared_states = &state_machine.states;
+    let transitions = &state_machine.transitions;
+    let terminal = &state_machine.terminal;
+    let mut arms = TokenStream2::new();
+    let pattern = |event: &Ident| variant_pattern(event_name, event, &state_machine.events);
+    let source = |state: &Ident| variant_pattern(state_name, state, declared_states);
+
+    for state in all_states {
+        for transition in transitions {
+            let explicit = match &transition.states {
+                StatePattern::Single { ident, .. } => ident == state,
+                StatePattern::Multiple { states } => states.iter().any(|(ident, _)| ident == state),
+                StatePattern::Wildcard => false,
+            };
+            if !explicit {
+                continue;
+            }
+
+            let events = transition.events.iter().map(pattern);
+            let next = match &transition.target {
+                TargetState::State(target) => {
+                    next_state(state_name, declared_states, target, false)
+                }
+                TargetState::Internal => next_state(state_name, declared_states, state, true),
+            };
+            let state = source(state);
+            arms.extend(quote! {
+                (#state, #(#events)|*) => #next,
+            });
+        }
+    }
+
+    let handled_specifically = |state: &Ident, event: &Ident| {
+        transitions.iter().any(|transition| {
+            !matches!(transition.states, StatePattern::Wildcard)
+                && transition.events.contains(event)
+                && expand_sources(&transition.states, all_states, terminal).contains(&state)
+        })
+    };
+
+    let wildcard_states: Vec<&Ident> = all_states
+        .iter()
+        .filter(|state| !terminal.contains(state))
+        .collect();
+
+    for transition in transitions {
+        if !matches!(transition.states, StatePattern::Wildcard) {
+            continue;
+        }
+
+        let target = match &transition.target {
+            TargetState::State(target) => target,
+            TargetState::Internal => {
+                for event in &transition.events {
+                    for state in &wildcard_states {
+                        if !handled_specifically(state, event) {
+                            let event = pattern(event);
+                            let next = next_state(state_name, declared_states, state, true);
+                            let state = source(state);
+                            arms.extend(quote! {
+                                (#state, #event) => #next,
+                            });
+                        }
+                    }
+                }
+                continue;
+            }
+        };
+
+        let next = next_state(state_name, declared_states, target, false);
+        if !terminal.is_empty() {
+            for event in &transition.events {
+                let states: Vec<TokenStream2> = wildcard_states
+                    .iter()
+                    .filter(|state| !handled_specifically(state, event))
+                    .map(|state| source(state))
+                    .collect();
+                if states.is_empty() {
+                    continue;
+                }
+                let event = pattern(event);
+                arms.extend(quote! {
+                    (#(#states)|*, #event) => #next,
+                });
+            }
+            continue;
+        }
+
+        let events: Vec<TokenStream2> = transition
+            .events
+            .iter()
+            .filter(|event| {
+                wildcard_states
+                    .iter()
+                    .any(|state| !handled_specifically(state, event))
+            })
+            .map(pattern)
+            .collect();
+        if events.is_empty() {
+            continue;
+        }
+
+        arms.extend(quote! {
+            (_, #(#events)|*) => #next,
+        });
+    }
+
+    if expand_edges(transitions, all_states, terminal).len() < all_states.len() * all_events.len() {
+        arms.extend(quote! {
+            _ => ::core::option::Option::None,
+        });
+    }
+
+    quote! {
+        match (self, event) {
+            #arms
+        }
+    }
+}
+
+fn generate_transition_table(
+    backend: &Ident,
+    state_name: &Ident,
+    all_states: &[Ident],
+    all_events: &[Ident],
+    terminal: &[Ident],
+    transitions: &[Transition],
+) -> Result<TokenStream2> {
+    if all_states.len() > usize::from(u8::MAX - 1) {
+        return Err(Error::new(
+            backend.span(),
+            format!(
+                "codegen: table supports at most {} states, but this machine has {}\n\
+                 help: use `codegen: match` instead",
+                u8::MAX - 1,
+                all_states.len()
+            ),
+        ));
+    }
+
+    let mut rows = Vec::new();
+    for state in all_states {
+        let cells = all_events.iter().map(|event| {
+            let transition = ordered_transitions(transitions).find(|transition| {
+                transition.events.contains(event)
+                    && expand_sources(&transition.states, all_states, terminal).contains(&state)
+            });
+            match transition.map(|transition| &transition.target) {
+                None => quote! { NONE },
+                Some(TargetState::Internal) => quote! { INTERNAL },
+                Some(TargetState::State(target)) => {
+                    let index = all_states.iter().position(|state| state == target).unwrap();
+                    let index = proc_macro2::Literal::u8_unsuffixed(index as u8);
+                    quote! { #index }
+                }
+            }
+        });
+        rows.push(quote! { [#(#cells),*] });
+    }
+
+    let state_count = all_states.len();
+    let event_count = all_events.len();
+    let decode_indices = (0..state_count - 1).map(proc_macro2::Literal::usize_unsuffixed);
+    let (last_state, decode_states) = all_states.split_last().unwrap();
+
+    Ok(quote! {
+        const NONE: u8 = u8::MAX;
+        const INTERNAL: u8 = u8::MAX - 1;
+        const TABLE: [[u8; #event_count]; #state_count] = [#(#rows),*];
+
+        let state_index = self.index();
+        let event_index = event.index();
+
+        let next = match TABLE[state_index][event_index] {
+            NONE => return ::core::option::Option::None,
+            INTERNAL => state_index,
+            next => next as usize,
+        };
+
+        ::core::option::Option::Some(match next {
+            #(#decode_indices => #state_name::#decode_states,)*
+            _ => #state_name::#last_state,
+        })
+    })
+}
+
+fn generate_dot(
+    state_name: &Ident,
+    all_states: &[Ident],
+    initial_state: &Ident,
+    terminal: &[Ident],
+    transitions: &[Transition],
+) -> String {
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph {} {{", state_name);
+    let _ = writeln!(dot, "    __start [shape=point];");
+
+    for state in all_states {
+        if terminal.contains(state) {
+            let _ = writeln!(dot, "    {} [shape=doublecircle];", state);
+        } else {
+            let _ = writeln!(dot, "    {};", state);
+        }
+    }
+
+    if transitions
+        .iter()
+        .any(|transition| matches!(transition.states, StatePattern::Wildcard))
+    {
+        let label = if terminal.is_empty() {
+            "*".to_string()
+        } else {
+            let excluded = terminal
+                .iter()
+                .map(|state| state.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("* except {}", excluded)
+        };
+        let _ = writeln!(
+            dot,
+            "    __any [label=\"{}\", shape=box, style=dashed];",
+            label
+        );
+    }
+
+    let _ = writeln!(dot, "    __start -> {};", initial_state);
+
+    for transition in transitions {
+        let label = transition
+            .events
+            .iter()
+            .map(|event| event.to_string())
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        let sources: Vec<String> = match &transition.states {
+            StatePattern::Single { ident, .. } => vec![ident.to_string()],
+            StatePattern::Multiple { states } => {
+                states.iter().map(|(ident, _)| ident.to_string()).collect()
+            }
+            StatePattern::Wildcard => vec!["__any".to_string()],
+        };
+
+        let style = match (&transition.states, &transition.target) {
+            (_, TargetState::Internal) => ", style=dotted",
+            (StatePattern::Wildcard, _) => ", style=dashed",
+            _ => "",
+        };
+
+        for source in &sources {
+            let target = match &transition.target {
+                TargetState::State(target) => target.to_string(),
+                TargetState::Internal => source.clone(),
+            };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"{}\"{}];",
+                source, target, label, style
+            );
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn read_manifest_file(path: &LitStr) -> Result<(String, String)> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| Error::new(path.span(), "CARGO_MANIFEST_DIR is not set"))?;
+    let full_path = std::path::Path::new(&manifest_dir).join(path.value());
+    let full_path = full_path.to_string_lossy().into_owned();
+
+    let source = std::fs::read_to_string(&full_path).map_err(|error| {
+        Error::new(
+            path.span(),
+            format!("failed to read '{}': {}", full_path, error),
+        )
+    })?;
+
+    Ok((full_path, source))
+}
+
+fn expand_file(path: &LitStr) -> Result<TokenStream2> {
+    let (full_path, source) = read_manifest_file(path)?;
+
+    // Tokens parsed from a string through the compiler all point at the macro
+    // call, so errors could not be located in the file. The fallback
+    // implementation gives every token its line and column in `source`
+    // instead. `fallback::force` is undocumented, doc(hidden) proc-macro2 API
+    // that switches every later expansion in the process, so the guard undoes
+    // it when this one pass ends, even if the pass panics.
+    struct ForceFallback;
+
+    impl Drop for ForceFallback {
+        fn drop(&mut self) {
+            proc_macro2::fallback::unforce();
+        }
+    }
+
+    let expanded = {
+        proc_macro2::fallback::force();
+        let _guard = ForceFallback;
+        syn::parse_str::<StateMachine>(&source)
+            .and_then(expand)
+            .map(|expanded| expanded.to_string())
+    };
+
+    let expanded = match expanded {
+        Ok(expanded) => expanded,
+        Err(errors) => {
+            let mut located = errors.into_iter().map(|error| {
+                let start = error.span().start();
+                Error::new(
+                    path.span(),
+                    format!(
+                        "{}:{}:{}: {}",
+                        path.value(),
+                        start.line,
+                        start.column + 1,
+                        error
+                    ),
+                )
+            });
+            let mut combined = located.next().expect("syn errors are never empty");
+            combined.extend(located);
+            return Err(combined);
+        }
+    };
+
+    // Fallback tokens cannot be handed to the compiler, so the expansion is
+    // carried across as text and tokenized again.
+    let expanded = syn::parse_str::<TokenStream2>(&expanded)?;
+
+    Ok(quote! {
+        #expanded
+        const _: &str = include_str!(#full_path);
+    })
+}
+
+#[proc_macro]
+pub fn statemachine(input: TokenStream) -> TokenStream {
+    let result = match parse_macro_input!(input as MachineInput) {
+        MachineInput::Inline(state_machine) => expand(*state_machine),
+        MachineInput::File(path) => expand_file(&path),
+    };
+
+    match result {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro]
+pub fn statemachine_from_scxml(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+
+    let result = read_manifest_file(&path).and_then(|(full_path, source)| {
+        let state_machine = scxml::parse(&source, &path)?;
+        let expanded = expand(state_machine)?;
+        Ok(quote! {
+            #expanded
+            const _: &str = include_str!(#full_path);
+        })
+    });
+
+    match result {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(state_machine: StateMachine) -> Result<TokenStream2> {
+    validate_no_duplicate_transitions(&state_machine.transitions)?;
+    validate_wildcard_overlaps(&state_machine.transitions)?;
+    validate_similar_names(&state_machine)?;
+
+    let state_name = if let Some(ref name) = state_machine.name {
+        Ident::new(&format!("{}State", name), name.span())
+    } else {
+        Ident::new("State", Span::call_site())
+    };
+
+    let event_name = if let Some(ref name) = state_machine.name {
+        Ident::new(&format!("{}Event", name), name.span())
+    } else {
+        Ident::new("Event", Span::call_site())
+    };
+
+    let mut all_states = alloc::vec::Vec::new();
+    let mut all_events = alloc::vec::Vec::new();
+    let mut initial_markers = Vec::new();
+
+    for transition in &state_machine.transitions {
+        match &transition.states {
+            StatePattern::Single { ident, initial } => {
+                if !all_states.iter().any(|s| s == ident) {
+                    all_states.push(ident.clone());
+                }
+                if *initial && !initial_markers.contains(ident) {
+                    initial_markers.push(ident.clone());
+                }
+            }
+            StatePattern::Multiple { states } => {
+                for (ident, initial) in states {
+                    if !all_states.iter().any(|s| s == ident) {
+                        all_states.push(ident.clone());
+                    }
+                    if *initial && !initial_markers.contains(ident) {
+                        initial_markers.push(ident.clone());
+                    }
+                }
+            }
+            StatePattern::Wildcard => {}
+        }
+
+        if let TargetState::State(ref target) = transition.target {
+            if !all_states.iter().any(|s| s == target) {
+                all_states.push(target.clone());
+            }
+        }
+
+        for event in &transition.events {
+            if !all_events.iter().any(|e| e == event) {
+                all_events.push(event.clone());
+            }
+        }
+    }
+
+    let initial_state = resolve_initial_state(
+        state_machine.initial.as_ref(),
+        &initial_markers,
+        &all_states,
+    )?;
+
+    validate_reachability(
+        &all_states,
+        &initial_state,
+        &state_machine.terminal,
+        &state_machine.transitions,
+        &state_machine.allow_unreachable,
+    )?;
+
+    validate_terminal_states(
+        &all_states,
+        &state_machine.transitions,
+        &state_machine.terminal,
+    )?;
+
+    validate_exhaustive(
+        &all_states,
+        &all_events,
+        &state_machine.terminal,
+        state_machine.exhaustive.as_ref(),
+        &state_machine.ignore,
+        &state_machine.transitions,
+    )?;
+
+    let dot = generate_dot(
+        &state_name,
+        &all_states,
+        &initial_state,
+        &state_machine.terminal,
+        &state_machine.transitions,
+    );
+
+    let mermaid = generate_mermaid(
+        &all_states,
+        &initial_state,
+        &state_machine.terminal,
+        &state_machine.transitions,
+    );
+    let plantuml = generate_plantuml(
+        &state_name,
+        &all_states,
+        &initial_state,
+        &state_machine.terminal,
+        &state_machine.transitions,
+    );
+    let scxml = generate_scxml(
+        state_machine.name.as_ref(),
+        &all_states,
+        &initial_state,
+        &state_machine.terminal,
+        &state_machine.transitions,
+    );
+
+    let default_derives = vec![
+        Ident::new("Debug", Span::call_site()),
+        Ident::new("Clone", Span::call_site()),
+        Ident::new("PartialEq", Span::call_site()),
+        Ident::new("Eq", Span::call_site()),
+    ];
+
+    let state_derives = state_machine
+        .derive_states
+        .as_ref()
+        .unwrap_or(&default_derives);
+
+    let event_derives = state_machine
+        .derive_events
+        .as_ref()
+        .unwrap_or(&default_derives);
+
+    let repr = state_machine.repr.as_ref();
+    let state_discriminants =
+        assign_discriminants("state", repr, &all_states, &state_machine.states)?;
+    let event_discriminants =
+        assign_discriminants("event", repr, &all_events, &state_machine.events)?;
+
+    let state_payloads = carries_data(&state_machine.states);
+    let event_payloads = carries_data(&state_machine.events);
+
+    let state_generics = payload_generics(&state_machine.generics, &state_machine.states);
+    let event_generics = payload_generics(&state_machine.generics, &state_machine.events);
+    if let Some(unused) = state_machine.generics.params.iter().find(|param| {
+        !state_generics.params.iter().any(|kept| kept == *param)
+            && !event_generics.params.iter().any(|kept| kept == *param)
+    }) {
+        return Err(Error::new_spanned(
+            unused,
+            format!(
+                "generic parameter `{}` is not used by any state or event payload\n\
+                 help: use it in a field of the `states` or `events` block, or remove it",
+                unused.to_token_stream()
+            ),
+        ));
+    }
+    let event_method_generics = method_generics(&state_machine.generics, &state_generics);
+    let (state_impl_generics, state_type_generics, state_where) = state_generics.split_for_impl();
+    let (_, event_type_generics, event_where) = event_generics.split_for_impl();
+    let (method_impl_generics, _, method_where) = event_method_generics.split_for_impl();
+    let state_pattern = |state: &Ident| variant_pattern(&state_name, state, &state_machine.states);
+
+    let repr_attribute = repr.map(|repr| quote! { #[repr(#repr)] });
+
+    let state_enum_variants = enum_variants(
+        &all_states,
+        &state_machine.states,
+        state_discriminants.as_deref(),
+    );
+    let state_enum = quote! {
+        #[derive(#(#state_derives),*)]
+        #repr_attribute
+        pub enum #state_name #state_generics #state_where {
+            #(#state_enum_variants),*
+        }
+    };
+
+    let event_enum_variants = enum_variants(
+        &all_events,
+        &state_machine.events,
+        event_discriminants.as_deref(),
+    );
+    let event_enum = quote! {
+        #[derive(#(#event_derives),*)]
+        #repr_attribute
+        pub enum #event_name #event_generics #event_where {
+            #(#event_enum_variants),*
+        }
+    };
+
+    let discriminant_impls = repr.map(|repr| {
+        let state_impls = generate_discriminant_impls(
+            &state_name,
+            &state_generics,
+            repr,
+            &all_states,
+            &state_machine.states,
+            state_discriminants.as_deref().unwrap(),
+        );
+        let event_impls = generate_discriminant_impls(
+            &event_name,
+            &event_generics,
+            repr,
+            &all_events,
+            &state_machine.events,
+            event_discriminants.as_deref().unwrap(),
+        );
+        quote! {
+            #state_impls
+            #event_impls
+        }
+    });
+
+    let process_event_body = match &state_machine.codegen {
+        Codegen::Match => generate_transition_match(
+            &state_name,
+            &event_name,
+            &all_states,
+            &all_events,
+            &state_machine,
+        ),
+        Codegen::Table(backend) if state_payloads => {
+            return Err(Error::new(
+                backend.span(),
+                "codegen: table does not support states that carry data\n\
+                 help: use `codegen: match` instead",
+            ));
+        }
+        Codegen::Table(backend) => generate_transition_table(
+            backend,
+            &state_name,
+            &all_states,
+            &all_events,
+            &state_machine.terminal,
+            &state_machine.transitions,
+        )?,
+    };
+
+    let (pending, next_state_type) = if state_payloads {
+        let pending = generate_pending(
+            &state_name,
+            &state_generics,
+            state_derives,
+            &state_machine.states,
+            &state_machine.transitions,
+        )?;
+        (Some(pending), pending_name(&state_name))
+    } else {
+        (None, state_name.clone())
+    };
+
+    let terminal_states: Vec<TokenStream2> =
+        state_machine.terminal.iter().map(state_pattern).collect();
+    let is_terminal = if terminal_states.is_empty() {
+        quote! { false }
+    } else {
+        quote! { matches!(*self, #(#terminal_states)|*) }
+    };
+
+    let default_impl = is_fieldless(&state_machine.states, &initial_state).then(|| {
+        quote! {
+            impl #state_impl_generics ::core::default::Default for #state_name #state_type_generics #state_where {
+                fn default() -> Self {
+                    #state_name::#initial_state
+                }
+            }
+        }
+    });
+
+    let info_name = if let Some(ref name) = state_machine.name {
+        Ident::new(&format!("{}TransitionInfo", name), name.span())
+    } else {
+        Ident::new("TransitionInfo", Span::call_site())
+    };
+    let transition_info = (!state_payloads && !event_payloads).then(|| {
+        generate_transition_info(
+            &info_name,
+            &state_name,
+            &event_name,
+            &state_machine.transitions,
+        )
+    });
+
+    let edges = expand_edges(
+        &state_machine.transitions,
+        &all_states,
+        &state_machine.terminal,
+    );
+    let can_process_arms = all_states.iter().map(|state| {
+        let events: Vec<TokenStream2> = edges
+            .iter()
+            .filter(|(source, _, _)| *source == state)
+            .map(|(_, event, _)| variant_pattern(&event_name, event, &state_machine.events))
+            .collect();
+        let state = state_pattern(state);
+        if events.is_empty() {
+            quote! { #state => false, }
+        } else {
+            quote! { #state => matches!(event, #(#events)|*), }
+        }
+    });
+
+    let graph_queries = (!state_payloads).then(|| {
+        let successor_arms = all_states.iter().map(|state| {
+            let successors = all_states.iter().filter(|other| {
+                edges
+                    .iter()
+                    .any(|(source, _, target)| *source == state && target == other)
+            });
+            quote! { #state_name::#state => &[#(#state_name::#successors),*], }
+        });
+        let predecessor_arms = all_states.iter().map(|state| {
+            let predecessors = all_states.iter().filter(|other| {
+                edges
+                    .iter()
+                    .any(|(source, _, target)| source == other && *target == state)
+            });
+            quote! { #state_name::#state => &[#(#state_name::#predecessors),*], }
+        });
+        quote! {
+            pub fn successors(&self) -> ::core::slice::Iter<'static, #state_name> {
+                let successors: &'static [#state_name] = match self {
+                    #(#successor_arms)*
+                };
+                successors.iter()
+            }
+
+            pub fn predecessors(&self) -> ::core::slice::Iter<'static, #state_name> {
+                let predecessors: &'static [#state_name] = match self {
+                    #(#predecessor_arms)*
+                };
+                predecessors.iter()
+            }
+        }
+    });
+
+    let event_sequences = (!event_payloads).then(|| {
+        let path_to_body = generate_path_to(
+            &state_name,
+            &event_name,
+            &all_states,
+            &state_machine.terminal,
+            &state_machine.states,
+            &state_machine.transitions,
+        );
+        quote! {
+            pub fn allowed_events(&self) -> impl ::core::iter::Iterator<Item = #event_name> + '_ {
+                ::core::iter::IntoIterator::into_iter(#event_name::ALL)
+                    .filter(move |event| self.can_process(event))
+            }
+
+            pub const fn path_to(&self, target: &#state_name) -> ::core::option::Option<&'static [#event_name]> {
+                #path_to_body
+            }
+        }
+    });
+
+    let process_events = (!state_payloads && !event_payloads).then(|| {
+        quote! {
+            pub const fn process_events(&self, events: &[#event_name]) -> ::core::option::Option<#state_name> {
+                let mut state = match self {
+                    #(#state_name::#all_states => #state_name::#all_states,)*
+                };
+                let mut index = 0;
+                while index < events.len() {
+                    let event = match events[index] {
+                        #(#event_name::#all_events => #event_name::#all_events,)*
+                    };
+                    state = match state.process_event(event) {
+                        ::core::option::Option::Some(next) => next,
+                        ::core::option::Option::None => return ::core::option::Option::None,
+                    };
+                    index += 1;
+                }
+                ::core::option::Option::Some(state)
+            }
+        }
+    });
+    let constness = (!event_payloads).then(|| quote! { const });
+
+    let rename_all = state_machine.rename_all.as_ref();
+    let state_strings = generate_string_impls(
+        &state_name,
+        &state_generics,
+        &all_states,
+        &state_machine.states,
+        rename_all,
+    )?;
+    let event_strings = generate_string_impls(
+        &event_name,
+        &event_generics,
+        &all_events,
+        &state_machine.events,
+        rename_all,
+    )?;
+
+    let state_enumeration = generate_enumeration(
+        &state_name,
+        &state_generics,
+        &all_states,
+        &state_machine.states,
+    );
+    let event_enumeration = generate_enumeration(
+        &event_name,
+        &event_generics,
+        &all_events,
+        &state_machine.events,
+    );
+
+    let expanded = quote! {
+        #state_enum
+        #event_enum
+        #pending
+        #state_enumeration
+        #event_enumeration
+        #transition_info
+        #state_strings
+        #event_strings
+        #discriminant_impls
+        #default_impl
+
+        impl #state_impl_generics #state_name #state_type_generics #state_where {
+            pub const DOT: &'static str = #dot;
+            pub const MERMAID: &'static str = #mermaid;
+            pub const PLANTUML: &'static str = #plantuml;
+            pub const SCXML: &'static str = #scxml;
+
+            pub fn is_terminal(&self) -> bool {
+                #is_terminal
+            }
+
+            pub const fn can_process #method_impl_generics (&self, event: &#event_name #event_type_generics) -> bool #method_where {
+                match self {
+                    #(#can_process_arms)*
+                }
+            }
+
+            #graph_queries
+
+            pub #constness fn process_event #method_impl_generics (
+                &self,
+                event: #event_name #event_type_generics,
+            ) -> ::core::option::Option<#next_state_type #state_type_generics> #method_where {
+                #process_event_body
+            }
+
+            #event_sequences
+            #process_events
+        }
     };
 
-    TokenStream::from(expanded)
+    Ok(expanded)
 }
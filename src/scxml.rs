@@ -0,0 +1,285 @@
+use crate::{StateMachine, StatePattern, TargetState, Transition};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use roxmltree::{Document, Node};
+use syn::{Error, Ident, LitStr, Result};
+
+struct Source<'a, 'input> {
+    document: &'a Document<'input>,
+    path: &'a LitStr,
+}
+
+impl Source<'_, '_> {
+    fn error(&self, node: Node, message: String) -> Error {
+        let position = self.document.text_pos_at(node.range().start);
+        Error::new(
+            self.path.span(),
+            format!(
+                "{}:{}:{}: {}",
+                self.path.value(),
+                position.row,
+                position.col,
+                message
+            ),
+        )
+    }
+
+    fn ident(&self, node: Node, value: &str, what: &str) -> Result<Ident> {
+        syn::parse_str::<Ident>(value).map_err(|_| {
+            self.error(
+                node,
+                format!("{} '{}' is not a valid Rust identifier", what, value),
+            )
+        })?;
+        Ok(Ident::new(value, self.path.span()))
+    }
+
+    fn check_attributes(&self, node: Node, allowed: &[&str]) -> Result<()> {
+        for attribute in node.attributes() {
+            if attribute.namespace().is_none() && !allowed.contains(&attribute.name()) {
+                return Err(self.error(
+                    node,
+                    format!(
+                        "unsupported attribute '{}' on <{}>",
+                        attribute.name(),
+                        node.tag_name().name()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn elements<'a>(&self, node: Node<'a, 'a>) -> Result<Vec<Node<'a, 'a>>> {
+        let mut elements = Vec::new();
+        for child in node.children() {
+            if child.is_element() {
+                elements.push(child);
+            } else if child.is_text() && !child.text().unwrap_or_default().trim().is_empty() {
+                return Err(self.error(
+                    child,
+                    format!("unexpected text inside <{}>", node.tag_name().name()),
+                ));
+            }
+        }
+        Ok(elements)
+    }
+
+    fn unsupported(&self, node: Node) -> Error {
+        let name = node.tag_name().name();
+        let note = match name {
+            "datamodel" | "data" | "script" | "assign" => {
+                "note: data belongs in the wrapper type, not the state machine"
+            }
+            "onentry" | "onexit" | "raise" | "send" | "log" | "if" | "foreach" | "cancel" => {
+                "note: executable content belongs in wrapper code, not the state machine"
+            }
+            "state" | "parallel" | "history" | "initial" | "final" => {
+                "note: only flat machines of <state> and <final> elements are supported"
+            }
+            _ => "note: supported elements are <scxml>, <state>, <final>, and <transition>",
+        };
+        self.error(
+            node,
+            format!("unsupported SCXML element <{}>\n{}", name, note),
+        )
+    }
+}
+
+pub(crate) fn parse(text: &str, path: &LitStr) -> Result<StateMachine> {
+    let document = Document::parse(text)
+        .map_err(|error| Error::new(path.span(), format!("{}: {}", path.value(), error)))?;
+    let source = Source {
+        document: &document,
+        path,
+    };
+
+    let root = document.root_element();
+    if root.tag_name().name() != "scxml" {
+        return Err(source.error(
+            root,
+            format!(
+                "expected <scxml> root element, found <{}>",
+                root.tag_name().name()
+            ),
+        ));
+    }
+    source.check_attributes(root, &["version", "name", "initial", "datamodel"])?;
+    if let Some(datamodel) = root.attribute("datamodel") {
+        if datamodel != "null" {
+            return Err(source.error(
+                root,
+                format!(
+                    "unsupported datamodel '{}'\nnote: only the null datamodel is supported",
+                    datamodel
+                ),
+            ));
+        }
+    }
+
+    let name = root
+        .attribute("name")
+        .map(|name| source.ident(root, name, "machine name"))
+        .transpose()?;
+
+    let mut states = Vec::new();
+    let mut terminal = Vec::new();
+    for element in source.elements(root)? {
+        match element.tag_name().name() {
+            "state" | "final" => {
+                source.check_attributes(element, &["id"])?;
+                let Some(id) = element.attribute("id") else {
+                    return Err(source.error(
+                        element,
+                        format!(
+                            "<{}> is missing an 'id' attribute",
+                            element.tag_name().name()
+                        ),
+                    ));
+                };
+                let ident = source.ident(element, id, "state id")?;
+                if states
+                    .iter()
+                    .any(|(state, _): &(Ident, Node)| *state == ident)
+                {
+                    return Err(source.error(element, format!("duplicate state id '{}'", id)));
+                }
+                if element.tag_name().name() == "final" {
+                    terminal.push(ident.clone());
+                }
+                states.push((ident, element));
+            }
+            _ => return Err(source.unsupported(element)),
+        }
+    }
+
+    let Some((first_state, _)) = states.first() else {
+        return Err(source.error(root, "<scxml> declares no states".into()));
+    };
+
+    let initial_state = match root.attribute("initial") {
+        Some(initial) => {
+            let ident = source.ident(root, initial, "initial state")?;
+            if !states.iter().any(|(state, _)| *state == ident) {
+                return Err(source.error(
+                    root,
+                    format!("initial state '{}' is not a declared state", initial),
+                ));
+            }
+            ident
+        }
+        None => first_state.clone(),
+    };
+
+    let mut transitions = Vec::new();
+    let mut initial_marked = false;
+
+    for (state, element) in &states {
+        for child in source.elements(*element)? {
+            if child.tag_name().name() != "transition" {
+                return Err(source.unsupported(child));
+            }
+            if element.tag_name().name() == "final" {
+                return Err(source.error(
+                    child,
+                    format!("final state '{}' cannot have outgoing transitions", state),
+                ));
+            }
+            if child.attribute("cond").is_some() {
+                return Err(source.error(
+                    child,
+                    "unsupported attribute 'cond' on <transition>\nnote: guards belong in wrapper code, not the state machine".into(),
+                ));
+            }
+            source.check_attributes(child, &["event", "target", "type"])?;
+            if let Some(executable) = source.elements(child)?.first() {
+                return Err(source.unsupported(*executable));
+            }
+
+            let Some(event_list) = child.attribute("event") else {
+                return Err(source.error(
+                    child,
+                    "eventless <transition> is not supported\nnote: every transition needs an 'event' attribute".into(),
+                ));
+            };
+            let events = event_list
+                .split_whitespace()
+                .map(|event| source.ident(child, event, "event"))
+                .collect::<Result<Vec<_>>>()?;
+            if events.is_empty() {
+                return Err(
+                    source.error(child, "<transition> has an empty 'event' attribute".into())
+                );
+            }
+
+            let target = match child.attribute("target") {
+                Some(target) => {
+                    let ident = source.ident(child, target, "transition target")?;
+                    if !states.iter().any(|(state, _)| *state == ident) {
+                        return Err(source.error(
+                            child,
+                            format!("transition target '{}' is not a declared state", target),
+                        ));
+                    }
+                    TargetState::State(ident)
+                }
+                None => TargetState::Internal,
+            };
+
+            let initial = !initial_marked && *state == initial_state;
+            initial_marked |= initial;
+
+            transitions.push(Transition {
+                states: StatePattern::Single {
+                    ident: state.clone(),
+                    initial,
+                },
+                events,
+                target,
+                precedence: None,
+                line: document.text_pos_at(child.range().start).row as usize,
+                weight: 1,
+            });
+        }
+    }
+
+    if !initial_marked {
+        return Err(source.error(
+            root,
+            format!(
+                "initial state '{}' has no outgoing transitions",
+                initial_state
+            ),
+        ));
+    }
+
+    for (state, element) in &states {
+        let mentioned = transitions.iter().any(|transition| {
+            matches!(&transition.states, StatePattern::Single { ident, .. } if ident == state)
+                || matches!(&transition.target, TargetState::State(target) if target == state)
+        });
+        if mentioned {
+            continue;
+        }
+        let message = if element.tag_name().name() == "final" {
+            format!(
+                "final state '{}' is never entered\nhelp: add a <transition> with target=\"{}\" or remove the state",
+                state, state
+            )
+        } else {
+            format!(
+                "state '{}' has no transitions\nhelp: add a <transition> into or out of '{}' or remove the state",
+                state, state
+            )
+        };
+        return Err(source.error(*element, message));
+    }
+
+    Ok(StateMachine {
+        name,
+        terminal,
+        transitions,
+        ..Default::default()
+    })
+}
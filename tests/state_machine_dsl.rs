@@ -1,4 +1,4 @@
-use stateless::statemachine;
+use stateless::{statemachine, statemachine_from_scxml};
 
 #[test]
 fn test_state_machine_dsl() {
@@ -201,3 +201,1208 @@ fn namespace_control() {
     }
     assert_eq!(enemy, EnemyState::Patrol);
 }
+
+#[test]
+fn dot_export() {
+    statemachine! {
+        name: Door,
+        terminal: [Broken],
+        transitions: {
+            *Closed + Open = Opened,
+            Opened + Close = Closed,
+            Closed | Opened + Lock = Locked,
+            Locked + Knock = _,
+            _ + Break = Broken,
+            #[precedence]
+            Locked + Break = _,
+        }
+    }
+
+    let dot = DoorState::DOT;
+    assert!(dot.starts_with("digraph DoorState {"));
+    assert!(dot.contains("__start -> Closed;"));
+    assert!(dot.contains("    Closed;\n"));
+    assert!(dot.contains("Closed -> Opened [label=\"Open\"];"));
+    assert!(dot.contains("Closed -> Locked [label=\"Lock\"];"));
+    assert!(dot.contains("Opened -> Locked [label=\"Lock\"];"));
+    assert!(dot.contains("Locked -> Locked [label=\"Knock\", style=dotted];"));
+    assert!(dot.contains("Broken [shape=doublecircle];"));
+    assert!(dot.contains("__any [label=\"* except Broken\", shape=box, style=dashed];"));
+    assert!(dot.contains("__any -> Broken [label=\"Break\", style=dashed];"));
+    assert!(dot.contains("Locked -> Locked [label=\"Break\", style=dotted];"));
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn mermaid_export() {
+    statemachine! {
+        name: Lamp,
+        transitions: {
+            *Off + Toggle = On,
+            On + Toggle | Unplug = Off,
+            On + Dim = _,
+            _ + Break = Broken,
+            #[precedence]
+            Off + Break = _,
+            Broken + Repair = Off,
+        }
+    }
+
+    let mermaid = LampState::MERMAID;
+    assert!(mermaid.starts_with("stateDiagram-v2\n    [*] --> Off\n"));
+    assert!(mermaid.contains("    Off --> On: Toggle\n"));
+    assert!(mermaid.contains("    On --> Off: Toggle\n"));
+    assert!(mermaid.contains("    On --> Off: Unplug\n"));
+    assert!(mermaid.contains("    On --> On: Dim\n"));
+    assert!(mermaid.contains("    Off --> Off: Break\n"));
+    assert!(!mermaid.contains("    Off --> Broken: Break\n"));
+    assert!(mermaid.contains("    On --> Broken: Break\n"));
+    assert!(mermaid.contains("    Broken --> Broken: Break\n"));
+    assert!(mermaid.contains("    Broken --> Off: Repair\n"));
+    assert_eq!(mermaid.matches(": Break\n").count(), 3);
+}
+
+#[test]
+fn plantuml_export() {
+    statemachine! {
+        name: Valve,
+        transitions: {
+            *Shut + Open = Flowing,
+            Flowing + Close = Shut,
+            Flowing + Adjust = _,
+            _ + Jam = Stuck,
+            #[precedence]
+            Flowing + Jam = Shut,
+            Stuck + Service = Shut,
+        }
+    }
+
+    let plantuml = ValveState::PLANTUML;
+    assert!(plantuml.starts_with("@startuml\ntitle ValveState\n"));
+    assert!(plantuml.contains("state Stuck\n"));
+    assert!(plantuml.contains("[*] --> Shut\n"));
+    assert!(plantuml.contains("Shut --> Flowing : Open\n"));
+    assert!(plantuml.contains("Flowing --> Flowing : Adjust\n"));
+    assert!(plantuml.contains("Shut --> Stuck : Jam\n"));
+    assert!(plantuml.contains("Flowing --> Shut : Jam\n"));
+    assert!(!plantuml.contains("Flowing --> Stuck : Jam\n"));
+    assert!(plantuml.contains("Stuck --> Stuck : Jam\n"));
+    assert!(plantuml.contains("Stuck --> Shut : Service\n"));
+    assert!(plantuml.ends_with("@enduml\n"));
+}
+
+#[test]
+fn scxml_export() {
+    statemachine! {
+        name: Pump,
+        transitions: {
+            *Stopped + Prime = Priming,
+            Priming + Primed = Pumping,
+            Pumping + Halt | Drain = Stopped,
+            Pumping + Pulse = _,
+            _ + Fault = Failed,
+            #[precedence]
+            Stopped + Fault = _,
+            Failed + Clear = Stopped,
+        }
+    }
+
+    let scxml = PumpState::SCXML;
+    assert!(scxml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(scxml.contains(
+        "<scxml xmlns=\"http://www.w3.org/2005/07/scxml\" version=\"1.0\" name=\"Pump\" initial=\"Stopped\">"
+    ));
+    assert!(scxml.contains(
+        "  <state id=\"Pumping\">\n    <transition event=\"Halt Drain\" target=\"Stopped\"/>\n    <transition event=\"Pulse\"/>\n    <transition event=\"Fault\" target=\"Failed\"/>\n  </state>\n"
+    ));
+    assert!(scxml.contains("    <transition event=\"Clear\" target=\"Stopped\"/>\n    <transition event=\"Fault\" target=\"Failed\"/>\n"));
+    assert!(scxml.contains(
+        "  <state id=\"Stopped\">\n    <transition event=\"Prime\" target=\"Priming\"/>\n    <transition event=\"Fault\"/>\n  </state>\n"
+    ));
+    assert!(scxml.ends_with("</scxml>\n"));
+}
+
+#[test]
+fn scxml_import() {
+    statemachine_from_scxml!("tests/machines/door.scxml");
+
+    let mut door = DoorState::default();
+    assert_eq!(door, DoorState::Closed);
+
+    for (event, expected) in [
+        (DoorEvent::Open, DoorState::Opened),
+        (DoorEvent::Close, DoorState::Closed),
+        (DoorEvent::Lock, DoorState::Locked),
+        (DoorEvent::Knock, DoorState::Locked),
+        (DoorEvent::Unlock, DoorState::Closed),
+    ] {
+        door = door.process_event(event).unwrap();
+        assert_eq!(door, expected);
+    }
+    assert_eq!(door.process_event(DoorEvent::Close), None);
+    assert!(!door.is_terminal());
+
+    door = DoorState::Locked.process_event(DoorEvent::Smash).unwrap();
+    assert!(door.is_terminal());
+
+    assert_eq!(DoorState::SCXML, include_str!("machines/door.scxml"));
+
+    let knock = DoorState::TRANSITIONS
+        .iter()
+        .find(|transition| transition.event == DoorEvent::Knock)
+        .unwrap();
+    assert_eq!(knock.line, 12);
+}
+
+#[test]
+fn dsl_from_file() {
+    statemachine!(file = "tests/machines/checkout.sm");
+
+    let mut checkout = CheckoutState::default();
+    assert_eq!(checkout, CheckoutState::Browsing);
+
+    for (event, expected) in [
+        (CheckoutEvent::AddItem, CheckoutState::Cart),
+        (CheckoutEvent::AddItem, CheckoutState::Cart),
+        (CheckoutEvent::RemoveItem, CheckoutState::Cart),
+        (CheckoutEvent::Proceed, CheckoutState::Payment),
+        (CheckoutEvent::Back, CheckoutState::Cart),
+        (CheckoutEvent::Proceed, CheckoutState::Payment),
+        (CheckoutEvent::Pay, CheckoutState::Confirmed),
+        (CheckoutEvent::Restart, CheckoutState::Browsing),
+        (CheckoutEvent::AddItem, CheckoutState::Cart),
+        (CheckoutEvent::Abandon, CheckoutState::Browsing),
+    ] {
+        checkout = checkout.process_event(event).unwrap();
+        assert_eq!(checkout, expected);
+    }
+
+    let lines: Vec<u32> = CheckoutState::TRANSITIONS
+        .iter()
+        .map(|transition| transition.line)
+        .collect();
+    assert_eq!(lines, [4, 5, 5, 6, 7, 8, 9, 10]);
+}
+
+#[test]
+fn allow_unreachable_states() {
+    statemachine! {
+        name: Migration,
+        allow_unreachable: [Legacy],
+        transitions: {
+            *Fresh + Upgrade = Current,
+            Legacy + Upgrade = Converting,
+            Converting + Finish = Current,
+            Current + Reset = Fresh,
+        }
+    }
+
+    let mut restored = MigrationState::Legacy;
+    for (event, expected) in [
+        (MigrationEvent::Upgrade, MigrationState::Converting),
+        (MigrationEvent::Finish, MigrationState::Current),
+        (MigrationEvent::Reset, MigrationState::Fresh),
+    ] {
+        restored = restored.process_event(event).unwrap();
+        assert_eq!(restored, expected);
+    }
+    assert_eq!(MigrationState::default(), MigrationState::Fresh);
+}
+
+#[test]
+fn terminal_states() {
+    statemachine! {
+        name: Job,
+        terminal: [Done, Failed],
+        transitions: {
+            *Queued + Start = Working,
+            Working + Progress = _,
+            Working + Finish = Done,
+            Queued | Working + Crash = Failed,
+            _ + Retry = Queued,
+            _ + Ping = _,
+        }
+    }
+
+    statemachine! {
+        name: Batch,
+        terminal: [Done, Failed],
+        codegen: table,
+        transitions: {
+            *Queued + Start = Working,
+            Working + Finish = Done,
+            Queued | Working + Crash = Failed,
+            _ + Retry = Queued,
+            _ + Ping = _,
+        }
+    }
+
+    let mut job = JobState::default();
+    let mut steps = 0;
+    for event in [
+        JobEvent::Start,
+        JobEvent::Progress,
+        JobEvent::Progress,
+        JobEvent::Finish,
+        JobEvent::Crash,
+    ] {
+        if job.is_terminal() {
+            break;
+        }
+        if let Some(new_state) = job.process_event(event) {
+            job = new_state;
+        }
+        steps += 1;
+    }
+    assert_eq!(job, JobState::Done);
+    assert_eq!(steps, 4);
+
+    let failed = JobState::Queued.process_event(JobEvent::Crash).unwrap();
+    assert!(failed.is_terminal());
+    assert!(!JobState::Working.is_terminal());
+    assert!(JobState::MERMAID.contains("    Done --> [*]\n    Failed --> [*]\n"));
+    assert!(JobState::SCXML.contains("  <final id=\"Done\"/>\n"));
+
+    assert_eq!(
+        JobState::Working.process_event(JobEvent::Retry),
+        Some(JobState::Queued)
+    );
+    assert_eq!(JobState::Done.process_event(JobEvent::Retry), None);
+    assert_eq!(JobState::Failed.process_event(JobEvent::Ping), None);
+    assert_eq!(JobState::Done.allowed_events().count(), 0);
+    assert!(!JobState::MERMAID.contains("Done --> Queued"));
+    assert!(!JobState::PLANTUML.contains("Failed --> Queued"));
+    assert_eq!(BatchState::Done.process_event(BatchEvent::Retry), None);
+    assert_eq!(BatchState::Failed.process_event(BatchEvent::Ping), None);
+    assert_eq!(
+        BatchState::Working.process_event(BatchEvent::Ping),
+        Some(BatchState::Working)
+    );
+}
+
+#[test]
+fn wildcard_precedence() {
+    statemachine! {
+        name: Player,
+        transitions: {
+            *Idle + Play = Running,
+            Running + Pause = Paused,
+            Paused + Play = Running,
+            _ + Reset = Idle,
+            #[precedence]
+            Running | Paused + Reset = Stopped,
+            Stopped + Play = Running,
+        }
+    }
+
+    assert_eq!(
+        PlayerState::Idle.process_event(PlayerEvent::Reset),
+        Some(PlayerState::Idle)
+    );
+    assert_eq!(
+        PlayerState::Running.process_event(PlayerEvent::Reset),
+        Some(PlayerState::Stopped)
+    );
+    assert_eq!(
+        PlayerState::Paused.process_event(PlayerEvent::Reset),
+        Some(PlayerState::Stopped)
+    );
+    assert_eq!(
+        PlayerState::Stopped.process_event(PlayerEvent::Reset),
+        Some(PlayerState::Idle)
+    );
+    assert_eq!(
+        PlayerState::Running.process_event(PlayerEvent::Pause),
+        Some(PlayerState::Paused)
+    );
+    assert_eq!(
+        PlayerState::Stopped.process_event(PlayerEvent::Play),
+        Some(PlayerState::Running)
+    );
+
+    assert!(PlayerState::MERMAID.contains("    Running --> Stopped: Reset\n"));
+    assert!(!PlayerState::MERMAID.contains("    Running --> Idle: Reset\n"));
+    assert!(PlayerState::MERMAID.contains("    Stopped --> Idle: Reset\n"));
+}
+
+#[test]
+fn exhaustive_machine() {
+    statemachine! {
+        name: Heater,
+        exhaustive: true,
+        terminal: [Broken],
+        ignore: {
+            Off + TurnOff,
+            Heating + TurnOn,
+            _ + Tick,
+        },
+        transitions: {
+            *Off + TurnOn = Heating,
+            Heating + TurnOff = Off,
+            Heating + Tick = _,
+            Heating + Overheat = Broken,
+            Off + Overheat = _,
+        }
+    }
+
+    let mut heater = HeaterState::default();
+    for event in [
+        HeaterEvent::TurnOff,
+        HeaterEvent::Tick,
+        HeaterEvent::TurnOn,
+        HeaterEvent::Tick,
+        HeaterEvent::TurnOn,
+        HeaterEvent::TurnOff,
+        HeaterEvent::Overheat,
+        HeaterEvent::TurnOn,
+        HeaterEvent::Overheat,
+    ] {
+        if let Some(new_state) = heater.process_event(event) {
+            heater = new_state;
+        }
+    }
+    assert_eq!(heater, HeaterState::Broken);
+}
+
+#[test]
+fn explicit_initial_state() {
+    statemachine! {
+        name: Elevator,
+        initial: Ground,
+        transitions: {
+            Ground + Up = Upper,
+            Upper + Down = Ground,
+        }
+    }
+
+    let mut elevator = ElevatorState::default();
+    assert_eq!(elevator, ElevatorState::Ground);
+
+    elevator = elevator.process_event(ElevatorEvent::Up).unwrap();
+    assert_eq!(elevator, ElevatorState::Upper);
+
+    elevator = elevator.process_event(ElevatorEvent::Down).unwrap();
+    assert_eq!(elevator, ElevatorState::Ground);
+}
+
+#[test]
+fn fully_covered_machine() {
+    statemachine! {
+        name: Switch,
+        derive_states: [Debug, PartialEq],
+        derive_events: [Debug],
+        transitions: {
+            *Off + Flip = On,
+            On + Flip = Off,
+            Off | On + Hold = _,
+            _ + Reset = Off,
+            #[precedence]
+            Off | On + Reset = Off,
+        }
+    }
+
+    let mut switch = SwitchState::default();
+    switch = switch.process_event(SwitchEvent::Flip).unwrap();
+    assert_eq!(switch, SwitchState::On);
+    switch = switch.process_event(SwitchEvent::Hold).unwrap();
+    assert_eq!(switch, SwitchState::On);
+    switch = switch.process_event(SwitchEvent::Reset).unwrap();
+    assert_eq!(switch, SwitchState::Off);
+}
+
+#[test]
+fn table_codegen_matches_default_codegen() {
+    statemachine! {
+        name: Table,
+        codegen: table,
+        transitions: {
+            *Idle + Start = Running,
+            Running + Pause | Stop = Idle,
+            Idle | Running + Connect = Connected,
+            Connected + Disconnect = Idle,
+            Connected + Tick = _,
+            _ + Reset = Idle,
+            #[precedence]
+            Connected + Reset = Running,
+        }
+    }
+
+    statemachine! {
+        name: Branch,
+        codegen: match,
+        transitions: {
+            *Idle + Start = Running,
+            Running + Pause | Stop = Idle,
+            Idle | Running + Connect = Connected,
+            Connected + Disconnect = Idle,
+            Connected + Tick = _,
+            _ + Reset = Idle,
+            #[precedence]
+            Connected + Reset = Running,
+        }
+    }
+
+    fn table_state(state: &BranchState) -> TableState {
+        match state {
+            BranchState::Idle => TableState::Idle,
+            BranchState::Running => TableState::Running,
+            BranchState::Connected => TableState::Connected,
+        }
+    }
+
+    let events = [
+        (TableEvent::Start, BranchEvent::Start),
+        (TableEvent::Pause, BranchEvent::Pause),
+        (TableEvent::Stop, BranchEvent::Stop),
+        (TableEvent::Connect, BranchEvent::Connect),
+        (TableEvent::Disconnect, BranchEvent::Disconnect),
+        (TableEvent::Tick, BranchEvent::Tick),
+        (TableEvent::Reset, BranchEvent::Reset),
+    ];
+
+    for state in [
+        BranchState::Idle,
+        BranchState::Running,
+        BranchState::Connected,
+    ] {
+        for (table_event, branch_event) in events.clone() {
+            assert_eq!(
+                table_state(&state).process_event(table_event),
+                state.process_event(branch_event).as_ref().map(table_state)
+            );
+        }
+    }
+}
+
+#[test]
+fn const_evaluation() {
+    mod boot {
+        use stateless::statemachine;
+
+        statemachine! {
+            name: Boot,
+            transitions: {
+                *Off + PowerOn = Booting,
+                Booting + Ready = Idle,
+                Idle + PowerOff = Off,
+                _ + Ping = _,
+            }
+        }
+    }
+
+    mod boot_table {
+        use stateless::statemachine;
+
+        statemachine! {
+            name: Boot,
+            codegen: table,
+            transitions: {
+                *Off + PowerOn = Booting,
+                Booting + Ready = Idle,
+                Idle + PowerOff = Off,
+                _ + Ping = _,
+            }
+        }
+    }
+
+    use boot::{BootEvent, BootState};
+
+    const BOOTED: Option<BootState> =
+        BootState::Off.process_events(&[BootEvent::PowerOn, BootEvent::Ping, BootEvent::Ready]);
+    static AFTER_POWER_ON: Option<BootState> = BootState::Off.process_event(BootEvent::PowerOn);
+    const REJECTED: Option<BootState> =
+        BootState::Off.process_events(&[BootEvent::Ready, BootEvent::PowerOff]);
+    const UNCHANGED: Option<BootState> = BootState::Idle.process_events(&[]);
+
+    assert_eq!(BOOTED, Some(BootState::Idle));
+    assert_eq!(AFTER_POWER_ON, Some(BootState::Booting));
+    assert_eq!(REJECTED, None);
+    assert_eq!(UNCHANGED, Some(BootState::Idle));
+
+    const TABLE_BOOTED: Option<boot_table::BootState> =
+        boot_table::BootState::Off.process_events(&[
+            boot_table::BootEvent::PowerOn,
+            boot_table::BootEvent::Ping,
+            boot_table::BootEvent::Ready,
+            boot_table::BootEvent::PowerOff,
+        ]);
+    assert_eq!(TABLE_BOOTED, Some(boot_table::BootState::Off));
+}
+
+#[test]
+fn stable_discriminants() {
+    statemachine! {
+        name: Link,
+        repr: u8,
+        states: {
+            Idle = 0,
+            Online,
+            Fault = 200,
+        },
+        events: {
+            Connect,
+            Drop,
+            Fail,
+            Reset = 0x7f,
+            Ping,
+        },
+        transitions: {
+            *Idle + Connect = Online,
+            Online + Drop = Idle,
+            Idle | Online + Fail = Fault,
+            Fault + Reset = Idle,
+            _ + Ping = _,
+        }
+    }
+
+    assert_eq!(LinkState::Idle.as_u8(), 0);
+    assert_eq!(LinkState::Online.as_u8(), 1);
+    assert_eq!(LinkState::Fault.as_u8(), 200);
+    assert_eq!(LinkState::Fault as u8, 200);
+
+    assert_eq!(LinkEvent::Connect.as_u8(), 0);
+    assert_eq!(LinkEvent::Drop.as_u8(), 1);
+    assert_eq!(LinkEvent::Fail.as_u8(), 2);
+    assert_eq!(LinkEvent::Reset.as_u8(), 127);
+    assert_eq!(LinkEvent::Ping.as_u8(), 128);
+
+    for state in [LinkState::Idle, LinkState::Online, LinkState::Fault] {
+        assert_eq!(LinkState::try_from(state.as_u8()), Ok(state.clone()));
+        for event in [
+            LinkEvent::Connect,
+            LinkEvent::Drop,
+            LinkEvent::Fail,
+            LinkEvent::Reset,
+            LinkEvent::Ping,
+        ] {
+            assert_eq!(LinkEvent::try_from(event.as_u8()), Ok(event.clone()));
+            assert_eq!(
+                state.can_process(&event),
+                state.process_event(event).is_some()
+            );
+        }
+    }
+
+    assert_eq!(LinkState::try_from(7), Err(TryFromLinkStateError(7)));
+    assert_eq!(LinkEvent::try_from(3), Err(TryFromLinkEventError(3)));
+    assert_eq!(
+        TryFromLinkStateError(7).to_string(),
+        "invalid LinkState discriminant: 7"
+    );
+
+    statemachine! {
+        name: Wide,
+        repr: u16,
+        states: {
+            Off = 1000,
+            On,
+        },
+        events: {
+            Toggle,
+        },
+        transitions: {
+            *Off + Toggle = On,
+            On + Toggle = Off,
+        }
+    }
+
+    assert_eq!(WideState::Off.as_u16(), 1000);
+    assert_eq!(WideState::On.as_u16(), 1001);
+    assert_eq!(WideEvent::Toggle.as_u16(), 0);
+    assert_eq!(WideState::try_from(1000u16), Ok(WideState::Off));
+    assert_eq!(
+        WideState::On.process_event(WideEvent::Toggle),
+        Some(WideState::Off)
+    );
+    assert_eq!(WideEvent::try_from(1u16), Err(TryFromWideEventError(1)));
+}
+
+#[test]
+fn enumeration_constants() {
+    statemachine! {
+        name: Menu,
+        transitions: {
+            *Closed + Open = Browsing,
+            Browsing + Select = Confirming,
+            Confirming + Confirm | Cancel = Closed,
+        }
+    }
+
+    const STATE_NAMES: [&str; MenuState::COUNT] = ["Closed", "Browsing", "Confirming"];
+
+    assert_eq!(MenuState::COUNT, 3);
+    assert_eq!(MenuEvent::COUNT, 4);
+    assert_eq!(
+        MenuState::ALL,
+        [
+            MenuState::Closed,
+            MenuState::Browsing,
+            MenuState::Confirming
+        ]
+    );
+
+    for (index, state) in MenuState::ALL.iter().enumerate() {
+        assert_eq!(state.index(), index);
+        assert_eq!(format!("{:?}", state), STATE_NAMES[state.index()]);
+        for (index, event) in MenuEvent::ALL.into_iter().enumerate() {
+            assert_eq!(event.index(), index);
+            assert_eq!(
+                state.can_process(&event),
+                state.process_event(event).is_some()
+            );
+        }
+    }
+
+    assert_eq!(
+        MenuEvent::ALL,
+        [
+            MenuEvent::Open,
+            MenuEvent::Select,
+            MenuEvent::Confirm,
+            MenuEvent::Cancel
+        ]
+    );
+}
+
+#[test]
+fn transition_introspection() {
+    let first_line = line!() + 4;
+    statemachine! {
+        name: Pump,
+        transitions: {
+            *Idle + Start = Priming,
+            Priming | Running + Tick = _,
+            Priming + Primed = Running,
+            _ + Stop = Idle,
+        }
+    }
+
+    let transitions: Vec<_> = PumpState::TRANSITIONS
+        .iter()
+        .map(|transition| {
+            (
+                transition.source.clone(),
+                transition.event.clone(),
+                transition.target.clone(),
+                transition.line - first_line,
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        transitions,
+        [
+            (
+                Some(PumpState::Idle),
+                PumpEvent::Start,
+                Some(PumpState::Priming),
+                0
+            ),
+            (Some(PumpState::Priming), PumpEvent::Tick, None, 1),
+            (Some(PumpState::Running), PumpEvent::Tick, None, 1),
+            (
+                Some(PumpState::Priming),
+                PumpEvent::Primed,
+                Some(PumpState::Running),
+                2
+            ),
+            (None, PumpEvent::Stop, Some(PumpState::Idle), 3),
+        ]
+    );
+
+    for transition in PumpState::TRANSITIONS {
+        for state in PumpState::ALL {
+            if transition.source.is_none() || transition.source == Some(state.clone()) {
+                let expected = transition.target.clone().unwrap_or(state.clone());
+                assert_eq!(
+                    state.process_event(transition.event.clone()),
+                    Some(expected)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn allowed_events() {
+    statemachine! {
+        name: Player,
+        terminal: [Ejected],
+        transitions: {
+            *Stopped + Play = Playing,
+            Playing + Pause = Paused,
+            Paused + Play = Playing,
+            Playing | Paused + Stop = Stopped,
+            Playing + Seek = _,
+            _ + Eject = Ejected,
+        }
+    }
+
+    assert_eq!(
+        PlayerState::Stopped.allowed_events().collect::<Vec<_>>(),
+        [PlayerEvent::Play, PlayerEvent::Eject]
+    );
+    assert_eq!(
+        PlayerState::Playing.allowed_events().collect::<Vec<_>>(),
+        [
+            PlayerEvent::Pause,
+            PlayerEvent::Stop,
+            PlayerEvent::Seek,
+            PlayerEvent::Eject
+        ]
+    );
+    assert_eq!(PlayerState::Ejected.allowed_events().count(), 0);
+
+    for state in PlayerState::ALL {
+        for event in PlayerEvent::ALL {
+            assert_eq!(
+                state.can_process(&event),
+                state.process_event(event).is_some()
+            );
+        }
+    }
+}
+
+#[test]
+fn successors_and_predecessors() {
+    statemachine! {
+        name: Valve,
+        transitions: {
+            *Closed + Open = Opening,
+            Opening + Opened = Open,
+            Open + Close = Closing,
+            Closing + Closed = Closed,
+            Opening | Closing + Stall = Jammed,
+            Jammed + Retry = _,
+            _ + Reset = Closed,
+        }
+    }
+
+    fn collect(states: core::slice::Iter<'static, ValveState>) -> Vec<ValveState> {
+        states.cloned().collect()
+    }
+
+    assert_eq!(
+        collect(ValveState::Closed.successors()),
+        [ValveState::Closed, ValveState::Opening]
+    );
+    assert_eq!(
+        collect(ValveState::Opening.successors()),
+        [ValveState::Closed, ValveState::Open, ValveState::Jammed]
+    );
+    assert_eq!(
+        collect(ValveState::Jammed.successors()),
+        [ValveState::Closed, ValveState::Jammed]
+    );
+    assert_eq!(
+        collect(ValveState::Jammed.predecessors()),
+        [ValveState::Opening, ValveState::Closing, ValveState::Jammed]
+    );
+    assert_eq!(collect(ValveState::Closed.predecessors()), ValveState::ALL);
+    assert_eq!(
+        collect(ValveState::Open.predecessors()),
+        [ValveState::Opening]
+    );
+
+    for state in ValveState::ALL {
+        for event in ValveEvent::ALL {
+            if let Some(next) = state.process_event(event) {
+                assert!(state.successors().any(|successor| *successor == next));
+                assert!(next.predecessors().any(|predecessor| *predecessor == state));
+            }
+        }
+    }
+}
+
+#[test]
+fn shortest_event_paths() {
+    statemachine! {
+        name: Robot,
+        terminal: [Decommissioned],
+        transitions: {
+            *Docked + Undock = Idle,
+            Idle + Move = Moving,
+            Moving + Arrive = Working,
+            Working + Finish = Idle,
+            #[weight = 5]
+            Working | Moving + Abort = Docked,
+            Idle + Dock = Docked,
+            Idle + Retire = Decommissioned,
+            Docked + Ping = _,
+        }
+    }
+
+    assert_eq!(
+        RobotState::Docked.path_to(&RobotState::Working),
+        Some(&[RobotEvent::Undock, RobotEvent::Move, RobotEvent::Arrive][..])
+    );
+    assert_eq!(
+        RobotState::Working.path_to(&RobotState::Docked),
+        Some(&[RobotEvent::Finish, RobotEvent::Dock][..])
+    );
+    assert_eq!(RobotState::Idle.path_to(&RobotState::Idle), Some(&[][..]));
+    assert_eq!(RobotState::Decommissioned.path_to(&RobotState::Idle), None);
+
+    const RECOVERY: Option<&[RobotEvent]> = RobotState::Moving.path_to(&RobotState::Docked);
+    assert_eq!(
+        RECOVERY,
+        Some(&[RobotEvent::Arrive, RobotEvent::Finish, RobotEvent::Dock][..])
+    );
+
+    for state in RobotState::ALL {
+        for target in RobotState::ALL {
+            if let Some(path) = state.path_to(&target) {
+                assert_eq!(state.process_events(path), Some(target));
+            }
+        }
+        for event in RobotEvent::ALL {
+            assert_eq!(
+                state.can_process(&event),
+                state.process_event(event).is_some()
+            );
+        }
+    }
+
+    statemachine! {
+        name: Relay,
+        transitions: {
+            *First + Hop = Second,
+            #[weight = 18446744073709551615]
+            Second + Hop = Third,
+            #[weight = 18446744073709551615]
+            Third + Hop = First,
+        }
+    }
+
+    assert_eq!(
+        RelayState::Second.path_to(&RelayState::First),
+        Some(&[RelayEvent::Hop, RelayEvent::Hop][..])
+    );
+}
+
+#[test]
+fn string_conversions() {
+    use core::str::FromStr;
+
+    statemachine! {
+        name: Console,
+        transitions: {
+            *Idle + RunCommand = Running,
+            Running + Done = Idle,
+        }
+    }
+
+    assert_eq!(ConsoleState::Running.as_str(), "Running");
+    assert_eq!(ConsoleEvent::RunCommand.to_string(), "RunCommand");
+    assert_eq!("Idle".parse::<ConsoleState>(), Ok(ConsoleState::Idle));
+    assert_eq!(
+        ConsoleEvent::from_str("run_command"),
+        Err(ParseConsoleEventError)
+    );
+    assert_eq!(
+        ParseConsoleEventError.to_string(),
+        "invalid ConsoleEvent, expected one of: RunCommand, Done"
+    );
+
+    statemachine! {
+        name: Job,
+        rename_all: snake_case,
+        terminal: [Archived],
+        transitions: {
+            *Queued + Start = InProgress,
+            InProgress + HTTPTimeout = Queued,
+            InProgress + Finish = Done2Review,
+            Done2Review + Approve = Archived,
+        }
+    }
+
+    assert_eq!(
+        JobState::ALL.map(|state| state.as_str()),
+        ["queued", "in_progress", "done2_review", "archived"]
+    );
+    assert_eq!(
+        ParseJobEventError::VALID_NAMES,
+        ["start", "http_timeout", "finish", "approve"]
+    );
+    assert_eq!(format!("{}", JobEvent::HTTPTimeout), "http_timeout");
+
+    for state in JobState::ALL {
+        assert_eq!(state.to_string().parse::<JobState>(), Ok(state.clone()));
+        for event in JobEvent::ALL {
+            assert_eq!(event.as_str().parse::<JobEvent>(), Ok(event.clone()));
+            assert_eq!(
+                state.can_process(&event),
+                state.process_event(event).is_some()
+            );
+        }
+    }
+    assert_eq!("InProgress".parse::<JobState>(), Err(ParseJobStateError));
+
+    statemachine! {
+        name: Alarm,
+        rename_all: SCREAMING_SNAKE_CASE,
+        transitions: {
+            *Disarmed + Arm = ArmedAway,
+            ArmedAway + Disarm = Disarmed,
+        }
+    }
+
+    assert_eq!(AlarmState::ArmedAway.as_str(), "ARMED_AWAY");
+    assert_eq!(
+        AlarmState::Disarmed.process_event(AlarmEvent::Arm),
+        "ARMED_AWAY".parse().ok()
+    );
+    assert_eq!(
+        AlarmState::ArmedAway.process_event(AlarmEvent::from_str("DISARM").unwrap()),
+        Some(AlarmState::Disarmed)
+    );
+}
+
+#[test]
+fn payload_events() {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Packet {
+        bytes: Vec<u8>,
+    }
+
+    statemachine! {
+        name: Link,
+        events: {
+            Connect(u32),
+            Data { packet: Packet },
+        },
+        transitions: {
+            *Offline + Connect = Online,
+            Online + Data = _,
+            Online + Disconnect = Offline,
+            _ + Reset = Offline,
+        }
+    }
+
+    struct Connection {
+        state: LinkState,
+        peer: u32,
+        received: usize,
+    }
+
+    impl Connection {
+        fn handle(&mut self, event: LinkEvent) {
+            if !self.state.can_process(&event) {
+                return;
+            }
+            match &event {
+                LinkEvent::Connect(peer) if *peer == 0 => return,
+                LinkEvent::Connect(peer) => self.peer = *peer,
+                LinkEvent::Data { packet } => self.received += packet.bytes.len(),
+                LinkEvent::Disconnect | LinkEvent::Reset => self.peer = 0,
+            }
+            self.state = self.state.process_event(event).unwrap();
+        }
+    }
+
+    let mut connection = Connection {
+        state: LinkState::default(),
+        peer: 0,
+        received: 0,
+    };
+
+    connection.handle(LinkEvent::Data {
+        packet: Packet { bytes: vec![1] },
+    });
+    assert_eq!(connection.received, 0);
+
+    connection.handle(LinkEvent::Connect(0));
+    assert_eq!(connection.state, LinkState::Offline);
+
+    connection.handle(LinkEvent::Connect(7));
+    assert_eq!(connection.state, LinkState::Online);
+    assert_eq!(connection.peer, 7);
+
+    connection.handle(LinkEvent::Data {
+        packet: Packet {
+            bytes: vec![1, 2, 3],
+        },
+    });
+    assert_eq!(connection.state, LinkState::Online);
+    assert_eq!(connection.received, 3);
+
+    connection.handle(LinkEvent::Disconnect);
+    assert_eq!(connection.state, LinkState::Offline);
+    assert_eq!(connection.peer, 0);
+
+    assert_eq!(
+        LinkState::Online.process_event(LinkEvent::Reset),
+        Some(LinkState::Offline)
+    );
+    assert_eq!(LinkEvent::Connect(3).as_str(), "Connect");
+    assert_eq!(LinkEvent::Disconnect.index(), 2);
+    assert_eq!(LinkEvent::COUNT, 4);
+}
+
+#[test]
+fn payload_states() {
+    statemachine! {
+        name: Session,
+        states: {
+            Connected { connection_id: u32 },
+            Retrying(u8),
+        },
+        transitions: {
+            *Idle + Connect = Connected,
+            Connected + Heartbeat = _,
+            Connected + Drop = Retrying,
+            Retrying + Connect = Connected,
+            Retrying + Drop = _,
+            _ + Close = Idle,
+        }
+    }
+
+    struct Client {
+        state: SessionState,
+        next_id: u32,
+    }
+
+    impl Client {
+        fn handle(&mut self, event: SessionEvent) -> bool {
+            let Some(pending) = self.state.process_event(event) else {
+                return false;
+            };
+            match pending {
+                SessionStatePending::Stay => {}
+                SessionStatePending::Ready(next) => self.state = next,
+                SessionStatePending::Connected(builder) => {
+                    self.next_id += 1;
+                    self.state = builder.build(self.next_id);
+                }
+                SessionStatePending::Retrying(builder) => self.state = builder.build(3),
+            }
+            true
+        }
+    }
+
+    let mut client = Client {
+        state: SessionState::default(),
+        next_id: 0,
+    };
+
+    assert!(!client.handle(SessionEvent::Heartbeat));
+    assert!(client.handle(SessionEvent::Connect));
+    assert_eq!(client.state, SessionState::Connected { connection_id: 1 });
+
+    assert!(client.handle(SessionEvent::Heartbeat));
+    assert_eq!(client.state, SessionState::Connected { connection_id: 1 });
+
+    assert!(client.handle(SessionEvent::Drop));
+    assert_eq!(client.state, SessionState::Retrying(3));
+    assert!(client.handle(SessionEvent::Drop));
+    assert_eq!(client.state, SessionState::Retrying(3));
+
+    assert!(client.handle(SessionEvent::Connect));
+    assert_eq!(client.state, SessionState::Connected { connection_id: 2 });
+    assert_eq!(client.state.as_str(), "Connected");
+    assert_eq!(client.state.index(), 1);
+    assert!(client.state.can_process(&SessionEvent::Close));
+    assert_eq!(
+        client.state.path_to(&SessionState::Retrying(0)),
+        Some(&[SessionEvent::Drop][..])
+    );
+
+    assert!(client.handle(SessionEvent::Close));
+    assert_eq!(client.state, SessionState::Idle);
+    assert_eq!(
+        client.state.allowed_events().collect::<Vec<_>>(),
+        [SessionEvent::Connect, SessionEvent::Close]
+    );
+    assert_eq!(SessionState::COUNT, 3);
+}
+
+#[test]
+fn generic_machines() {
+    statemachine! {
+        name: Protocol,
+        generics: <T, S: Clone>,
+        where: {
+            T: Clone + PartialEq,
+        },
+        states: {
+            Established { session: S },
+        },
+        events: {
+            Handshake(S),
+            Data(T),
+        },
+        transitions: {
+            *Closed + Handshake = Established,
+            Established + Data = _,
+            _ + Close = Closed,
+        }
+    }
+
+    fn run<T, S>(session: S, payload: T) -> ProtocolState<S>
+    where
+        T: Clone + PartialEq + core::fmt::Debug,
+        S: Clone + core::fmt::Debug + PartialEq,
+    {
+        let mut state = ProtocolState::default();
+        for event in [
+            ProtocolEvent::Data(payload.clone()),
+            ProtocolEvent::Handshake(session),
+            ProtocolEvent::Data(payload),
+        ] {
+            let handshake = match &event {
+                ProtocolEvent::Handshake(session) => Some(session.clone()),
+                _ => None,
+            };
+            match state.process_event(event) {
+                None | Some(ProtocolStatePending::Stay) => {}
+                Some(ProtocolStatePending::Ready(next)) => state = next,
+                Some(ProtocolStatePending::Established(builder)) => {
+                    state = builder.build(handshake.unwrap());
+                }
+            }
+        }
+        state
+    }
+
+    assert_eq!(
+        run(7u16, [1u8, 2, 3]),
+        ProtocolState::Established { session: 7u16 }
+    );
+    assert_eq!(
+        run("tcp", String::from("payload")),
+        ProtocolState::Established { session: "tcp" }
+    );
+
+    let established = ProtocolState::Established { session: 1u8 };
+    assert!(established.can_process(&ProtocolEvent::<&str, u8>::Data("bytes")));
+    assert_eq!(
+        established.process_event(ProtocolEvent::<&str, u8>::Close),
+        Some(ProtocolStatePending::Ready(ProtocolState::Closed))
+    );
+    assert_eq!(ProtocolEvent::<u8, u8>::Data(1).as_str(), "Data");
+    assert!(!established.is_terminal());
+}
+
+#[test]
+fn allow_similar_names() {
+    statemachine! {
+        name: Metronome,
+        allow_similar: [Tock],
+        transitions: {
+            *Idle + Tick = Running,
+            Running + Tick = _,
+            Running + Tock = Idle,
+        }
+    }
+
+    let mut state = MetronomeState::default();
+    for (event, expected) in [
+        (MetronomeEvent::Tick, MetronomeState::Running),
+        (MetronomeEvent::Tick, MetronomeState::Running),
+        (MetronomeEvent::Tock, MetronomeState::Idle),
+    ] {
+        state = state.process_event(event).unwrap();
+        assert_eq!(state, expected);
+    }
+    assert_eq!(state.process_event(MetronomeEvent::Tock), None);
+}
@@ -0,0 +1,11 @@
+use stateless::statemachine;
+
+statemachine! {
+    exhaustive: true,
+    transitions: {
+        *Idle + Start = Running,
+        Running + Stop = Idle,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use stateless::statemachine;
+
+statemachine! {
+    exhaustive: true,
+    ignore: {
+        *Idle + Stop,
+        Running + Start,
+    },
+    transitions: {
+        *Idle + Start = Running,
+        Running + Stop = Idle,
+    }
+}
+
+fn main() {}
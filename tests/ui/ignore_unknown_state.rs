@@ -0,0 +1,15 @@
+use stateless::statemachine;
+
+statemachine! {
+    exhaustive: true,
+    ignore: {
+        Paused + Start,
+        Idle + Stop,
+    },
+    transitions: {
+        *Idle + Start = Running,
+        Running + Stop = Idle,
+    }
+}
+
+fn main() {}
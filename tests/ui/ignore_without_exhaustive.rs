@@ -0,0 +1,13 @@
+use stateless::statemachine;
+
+statemachine! {
+    ignore: {
+        Idle + Stop,
+    },
+    transitions: {
+        *Idle + Start = Running,
+        Running + Stop = Idle,
+    }
+}
+
+fn main() {}
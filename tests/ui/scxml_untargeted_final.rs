@@ -0,0 +1,6 @@
+use stateless::statemachine_from_scxml;
+
+// trybuild builds from target/tests/trybuild/stateless, so the path climbs back to the crate root.
+statemachine_from_scxml!("../../../../tests/ui/scxml_untargeted_final.scxml");
+
+fn main() {}